@@ -1,11 +1,13 @@
+use chessica_protocol::board::Board;
 use chessica_protocol::types::*;
 
-pub fn create_initial_board() -> Vec<Option<Piece>> {
-    let mut board = vec![None; 64];
+pub fn create_initial_board() -> Board {
+    let mut board = Board::default();
 
-    let set_piece = |board: &mut Vec<Option<Piece>>, rank: u8, file: u8, piece: Piece| {
-        let index = (rank * 8 + file) as usize;
-        board[index] = Some(piece);
+    let set_piece = |board: &mut Board, rank: u8, file: u8, piece: Piece| {
+        if let Some(square) = Square::new(rank * 8 + file) {
+            board.set(square, Some(piece));
+        }
     };
 
     set_piece(
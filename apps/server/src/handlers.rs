@@ -1,19 +1,23 @@
-use crate::game_logic::handle_move;
+use crate::create_board::create_initial_board;
+use crate::room::{spawn_room, RoomAction, RoomCommand};
 use crate::state::AppState;
 use crate::state::Client;
 use crate::state::GameRoom;
+use crate::state::JoinOutcome;
 use axum::{
     extract::{
         ws::{WebSocket, WebSocketUpgrade},
-        Json, State,
+        Json, Path, Query, State,
     },
+    http::{header, StatusCode},
     response::IntoResponse,
 };
+use chessica_protocol::pgn::{parse_pgn, PgnError};
 use chessica_protocol::types::*;
+use chessica_protocol::PROTOCOL_VERSION;
 use futures_util::{SinkExt, StreamExt};
-use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 use uuid::Uuid;
 
 pub async fn ws_handler(
@@ -60,14 +64,22 @@ pub async fn handle_socket(socket: WebSocket, app_state: Arc<AppState>) {
         );
 
         let parsed = serde_json::from_str::<ClientMessage>(&msg);
-        let mut send_error = |message: String| {
-            let err = ServerMessage::Error { message };
-            let _ = tx.send(Message::Text(serde_json::to_string(&err).unwrap().into()));
+        let send_message = |message: &ServerMessage| {
+            let _ = tx.send(Message::Text(
+                serde_json::to_string(message).unwrap().into(),
+            ));
         };
+        let send_error = |message: String| send_message(&ServerMessage::Error { message });
 
-        match parsed {
+        let action = match parsed {
             Ok(ClientMessage::Identify { id }) => {
+                // The room knows this socket by the id it joined with.
+                if joined_game_id.is_some() && client_id != Some(id) {
+                    send_error("Already joined a game under another id".into());
+                    continue;
+                }
                 client_id = Some(id);
+                continue;
             }
             Ok(ClientMessage::JoinGame { game_id }) => {
                 let id = match client_id {
@@ -77,92 +89,84 @@ pub async fn handle_socket(socket: WebSocket, app_state: Arc<AppState>) {
                         continue;
                     }
                 };
-                let mut rooms = app_state.game_rooms.lock().unwrap();
-                if let Some(room) = rooms.get_mut(&game_id) {
-                    let client = Client {
-                        id,
-                        color: None,
-                        sender: tx.clone(),
-                    };
-                    room.add_client(client);
-                    joined_game_id = Some(game_id);
-                    if let Some(assigned) = room
-                        .clients
-                        .iter()
-                        .find(|c| c.id == id)
-                        .and_then(|c| c.color)
-                    {
-                        let _ = tx.send(Message::Text(
-                            serde_json::to_string(&ServerMessage::ColorAssigned {
-                                color: assigned,
-                            })
-                            .unwrap()
-                            .into(),
-                        ));
-                    }
-                    if room.can_start_game() {
-                        room.start_game();
-                        room.broadcast(&ServerMessage::GameStarted);
-                        if let Some(state) = room.get_game_state() {
-                            room.broadcast(&ServerMessage::GameState { state });
-                        }
-                    } else {
-                        room.broadcast(&ServerMessage::WaitingForPlayers {
-                            connected_count: room.get_client_count(),
-                        });
-                    }
-                } else {
+                let Some(room) = app_state.room(&game_id) else {
                     send_error("Game not found".into());
-                }
-            }
-            Ok(ClientMessage::MakeMove { move_ }) => {
-                let id = match client_id {
-                    Some(id) => id,
-                    None => {
-                        send_error("Identify first".into());
-                        continue;
-                    }
+                    continue;
                 };
-                let game_id = match joined_game_id {
-                    Some(gid) => gid,
-                    None => {
-                        send_error("Join a game first".into());
-                        continue;
-                    }
+                let client = Client {
+                    id,
+                    color: None,
+                    sender: tx.clone(),
                 };
-                let mut rooms = app_state.game_rooms.lock().unwrap();
-                if let Some(room) = rooms.get_mut(&game_id) {
-                    let move_result = room.handle_move(&id, &move_);
-                    if let Some((move_, game_state)) = move_result {
-                        room.broadcast(&ServerMessage::MoveMade {
-                            move_: move_.clone(),
-                        });
-                        room.broadcast(&ServerMessage::GameState { state: game_state });
-                    } else {
-                        send_error("Invalid move or not your turn".into());
-                    }
-                } else {
+                let (reply, outcome) = oneshot::channel();
+                if !room.send(RoomCommand::Join { client, reply }) {
                     send_error("Game not found".into());
+                    continue;
+                }
+                match outcome.await {
+                    Ok(JoinOutcome::RoomFull) => {}
+                    // The room stopped before it could answer.
+                    Err(_) => send_error("Game not found".into()),
+                    Ok(_) => {
+                        // A socket plays in one room at a time.
+                        let previous = joined_game_id.replace(game_id);
+                        if let Some(previous) = previous.filter(|&previous| previous != game_id) {
+                            if let Some(room) = app_state.room(&previous) {
+                                room.send(RoomCommand::Leave { sender: tx.clone() });
+                            }
+                        }
+                    }
                 }
+                continue;
             }
-            Ok(ClientMessage::Resign) => {
-                send_error("Resign not implemented".into());
+            Ok(ClientMessage::TakePiece { .. }) => {
+                send_error(format!(
+                    "TakePiece is deprecated since protocol 1.3.0 (server speaks {}); \
+                     captures are made with MakeMove",
+                    PROTOCOL_VERSION
+                ));
+                continue;
             }
+            Ok(ClientMessage::MakeMove { move_ }) => RoomAction::MakeMove(move_),
+            Ok(ClientMessage::Resign) => RoomAction::Resign,
+            Ok(ClientMessage::ClaimDraw) => RoomAction::ClaimDraw,
+            Ok(ClientMessage::OfferDraw) => RoomAction::OfferDraw,
+            Ok(ClientMessage::AcceptDraw) => RoomAction::AcceptDraw,
+            Ok(ClientMessage::DeclineDraw) => RoomAction::DeclineDraw,
+            Ok(ClientMessage::RequestTakeback) => RoomAction::RequestTakeback,
+            Ok(ClientMessage::AcceptTakeback) => RoomAction::AcceptTakeback,
+            Ok(ClientMessage::DeclineTakeback) => RoomAction::DeclineTakeback,
             Err(e) => {
                 send_error(format!("Invalid message: {}", e));
+                continue;
+            }
+        };
+
+        let (id, game_id) = match (client_id, joined_game_id) {
+            (None, _) => {
+                send_error("Identify first".into());
+                continue;
+            }
+            (_, None) => {
+                send_error("Join a game first".into());
+                continue;
             }
+            (Some(id), Some(game_id)) => (id, game_id),
+        };
+        let sent = app_state.room(&game_id).is_some_and(|room| {
+            room.send(RoomCommand::Action {
+                client_id: id,
+                action,
+            })
+        });
+        if !sent {
+            send_error("Game not found".into());
         }
     }
 
-    if let (Some(id), Some(game_id)) = (client_id, joined_game_id) {
-        let mut rooms = app_state.game_rooms.lock().unwrap();
-        if let Some(room) = rooms.get_mut(&game_id) {
-            room.remove_client(&id);
-            if !room.is_game_started() {
-                room.broadcast(&ServerMessage::WaitingForPlayers {
-                    connected_count: room.get_client_count(),
-                });
-            }
+    if let Some(game_id) = joined_game_id {
+        if let Some(room) = app_state.room(&game_id) {
+            room.send(RoomCommand::Leave { sender: tx.clone() });
         }
     }
 
@@ -173,16 +177,133 @@ pub async fn handle_socket(socket: WebSocket, app_state: Arc<AppState>) {
     send_task.abort();
 }
 
+/// Upper bounds on the time controls a game can be created with.
+const MAX_BASE_MS: u64 = 24 * 60 * 60 * 1000;
+const MAX_INCREMENT_MS: u64 = 60 * 60 * 1000;
+const MAX_DELAY_MS: u64 = 60 * 60 * 1000;
+
 pub async fn new_game_handler(
     State(app_state): State<Arc<AppState>>,
     Json(payload): Json<NewGameBody>,
-) -> impl IntoResponse {
-    let mut rooms = app_state.game_rooms.lock().unwrap();
-    let game_room = GameRoom::new(payload.color);
-    let game_id = game_room.game_id;
-    rooms.insert(game_id, game_room);
+) -> Result<Json<NewGameResponse>, (StatusCode, String)> {
+    if let Some(tc) = payload.time_control {
+        if tc.base_ms == 0 {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "A time control needs a base time".to_string(),
+            ));
+        }
+        if tc.base_ms > MAX_BASE_MS
+            || tc.increment_ms > MAX_INCREMENT_MS
+            || tc.delay.is_some_and(|delay| delay.ms > MAX_DELAY_MS)
+        {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!(
+                    "Time controls allow at most {} ms base time, {} ms increment and {} ms delay",
+                    MAX_BASE_MS, MAX_INCREMENT_MS, MAX_DELAY_MS
+                ),
+            ));
+        }
+    }
+    match payload.correspondence {
+        Some(_) if payload.time_control.is_some() => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Give either a time control or correspondence, not both".to_string(),
+            ))
+        }
+        Some(c) if c.days_per_move == 0 => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Correspondence games need at least one day per move".to_string(),
+            ))
+        }
+        _ => {}
+    }
+    let (initial_state, imported_moves) = match (payload.fen, payload.pgn) {
+        (Some(_), Some(_)) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                "Give either a FEN or a PGN, not both".to_string(),
+            ))
+        }
+        (Some(fen), None) => {
+            let initial_state = GameState::from_fen(&fen)
+                .map_err(|e| (StatusCode::BAD_REQUEST, format!("Invalid FEN: {}", e)))?;
+            (initial_state, vec![])
+        }
+        (None, Some(pgn)) => {
+            let invalid = |e: PgnError| (StatusCode::BAD_REQUEST, format!("Invalid PGN: {}", e));
+            let game = parse_pgn(&pgn).map_err(invalid)?;
+            let initial_state = game.initial_state().map_err(invalid)?;
+            let (moves, _) = game.replay().map_err(invalid)?;
+            println!("[HTTP] Imported PGN with {} plies", moves.len());
+            (initial_state, moves)
+        }
+        (None, None) => (GameState::new(create_initial_board()), vec![]),
+    };
+
+    let game_room = GameRoom::new(
+        payload.color,
+        initial_state,
+        imported_moves,
+        payload.time_control,
+        payload.correspondence,
+    );
+    let game_id = spawn_room(&app_state, game_room);
 
     let response = NewGameResponse { game_id };
 
-    axum::Json(response)
+    Ok(axum::Json(response))
+}
+
+/// Default and largest page sizes for `GET /api/games`.
+const DEFAULT_PAGE_SIZE: usize = 20;
+const MAX_PAGE_SIZE: usize = 100;
+
+pub async fn list_games_handler(
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<GameListQuery>,
+) -> Json<GameList> {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).min(MAX_PAGE_SIZE);
+    let mut games: Vec<GameSummary> = app_state
+        .game_rooms
+        .lock()
+        .unwrap()
+        .values()
+        .map(|room| room.summary().game)
+        .filter(|game| query.status.is_none_or(|status| game.status == status))
+        .filter(|game| {
+            query
+                .kind
+                .is_none_or(|kind| game.time_control_kind() == kind)
+        })
+        .collect();
+    games.sort_by(|a, b| {
+        b.created_at
+            .cmp(&a.created_at)
+            .then(a.game_id.cmp(&b.game_id))
+    });
+    let total = games.len();
+    let games = games.into_iter().skip(query.offset).take(limit).collect();
+    Json(GameList {
+        games,
+        total,
+        offset: query.offset,
+        limit,
+    })
+}
+
+pub async fn game_pgn_handler(
+    State(app_state): State<Arc<AppState>>,
+    Path(game_id): Path<Uuid>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let room = app_state.room(&game_id).ok_or(StatusCode::NOT_FOUND)?;
+    let (reply, pgn) = oneshot::channel();
+    if !room.send(RoomCommand::Pgn { reply }) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let pgn = pgn.await.map_err(|_| StatusCode::NOT_FOUND)?;
+    Ok(([(header::CONTENT_TYPE, "application/x-chess-pgn")], pgn))
 }
@@ -1,6 +1,6 @@
 mod create_board;
-mod game_logic;
 mod handlers;
+mod room;
 mod state;
 
 use axum::{
@@ -8,7 +8,7 @@ use axum::{
     routing::{get, put},
     Router,
 };
-use state::AppState;
+use state::{AppState, RoomTtls};
 use std::sync::{Arc, Mutex};
 use tokio::net::TcpListener;
 use tower_http::cors::{Any, CorsLayer};
@@ -17,8 +17,9 @@ use tower_http::cors::{Any, CorsLayer};
 async fn main() {
     let app_state = Arc::new(AppState {
         game_rooms: Mutex::new(std::collections::HashMap::new()),
-        client_rooms: Mutex::new(std::collections::HashMap::new()),
+        room_ttls: RoomTtls::from_env(),
     });
+    tokio::spawn(room::reap_rooms(app_state.clone()));
 
     let cors = CorsLayer::new()
         .allow_origin(Any)
@@ -28,6 +29,8 @@ async fn main() {
     let app = Router::new()
         .route("/ws", get(handlers::ws_handler))
         .route("/api/game", put(handlers::new_game_handler))
+        .route("/api/game/{id}/pgn", get(handlers::game_pgn_handler))
+        .route("/api/games", get(handlers::list_games_handler))
         .layer(cors)
         .with_state(app_state);
 
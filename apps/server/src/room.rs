@@ -0,0 +1,336 @@
+use crate::state::{AppState, Client, GameRoom, JoinOutcome, RoomError};
+use axum::extract::ws::Message;
+use chessica_protocol::types::*;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+use uuid::Uuid;
+
+// Every room runs on its own task and owns its `GameRoom` outright, so rooms
+// never wait on each other and a panic in one only takes that room down.
+// Sockets and HTTP handlers talk to a room through its `RoomHandle`.
+
+/// How often the reaper looks for expired rooms.
+const REAP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// What a player can do in a room they have joined.
+#[derive(Debug)]
+pub enum RoomAction {
+    MakeMove(Move),
+    Resign,
+    ClaimDraw,
+    OfferDraw,
+    AcceptDraw,
+    DeclineDraw,
+    RequestTakeback,
+    AcceptTakeback,
+    DeclineTakeback,
+}
+
+pub enum RoomCommand {
+    Join {
+        client: Client,
+        reply: oneshot::Sender<JoinOutcome>,
+    },
+    Action {
+        client_id: Uuid,
+        action: RoomAction,
+    },
+    Leave {
+        sender: mpsc::UnboundedSender<Message>,
+    },
+    Pgn {
+        reply: oneshot::Sender<String>,
+    },
+}
+
+/// What the registry knows about a room without asking its task, kept up to
+/// date by the task.
+#[derive(Debug, Clone)]
+pub struct RoomSummary {
+    pub game: GameSummary,
+    pub last_activity: Instant,
+}
+
+#[derive(Debug, Clone)]
+pub struct RoomHandle {
+    sender: mpsc::UnboundedSender<RoomCommand>,
+    summary: Arc<Mutex<RoomSummary>>,
+}
+
+impl RoomHandle {
+    /// Returns false if the room's task has stopped.
+    pub fn send(&self, command: RoomCommand) -> bool {
+        self.sender.send(command).is_ok()
+    }
+
+    pub fn summary(&self) -> RoomSummary {
+        self.summary.lock().unwrap().clone()
+    }
+}
+
+/// Starts `room` on its own task and registers it in `app_state`. The room
+/// leaves the registry when its task ends, including by panicking.
+pub fn spawn_room(app_state: &Arc<AppState>, room: GameRoom) -> Uuid {
+    let game_id = room.game_id;
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let summary = Arc::new(Mutex::new(RoomSummary {
+        game: room.summary(),
+        last_activity: Instant::now(),
+    }));
+    app_state.game_rooms.lock().unwrap().insert(
+        game_id,
+        RoomHandle {
+            sender,
+            summary: summary.clone(),
+        },
+    );
+
+    let task = tokio::spawn(run_room(room, receiver, summary));
+    let app_state = app_state.clone();
+    tokio::spawn(async move {
+        if let Err(e) = task.await {
+            eprintln!("[Room] game_id={} stopped: {}", game_id, e);
+        }
+        app_state.game_rooms.lock().unwrap().remove(&game_id);
+    });
+    game_id
+}
+
+/// Removes rooms nobody is connected to that have been idle for longer than
+/// their TTL. Dropping a room's handle closes its command channel, which ends
+/// its task.
+pub async fn reap_rooms(app_state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(REAP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let ttls = app_state.room_ttls;
+        app_state
+            .game_rooms
+            .lock()
+            .unwrap()
+            .retain(|game_id, handle| {
+                let summary = handle.summary();
+                let expired = summary.game.players == 0
+                    && summary.game.spectators == 0
+                    && ttls
+                        .ttl(&summary.game)
+                        .is_some_and(|ttl| summary.last_activity.elapsed() >= ttl);
+                if expired {
+                    println!(
+                        "[Reaper] Removing {:?} room game_id={}",
+                        summary.game.status, game_id
+                    );
+                }
+                !expired
+            });
+    }
+}
+
+async fn run_room(
+    mut room: GameRoom,
+    mut commands: mpsc::UnboundedReceiver<RoomCommand>,
+    summary: Arc<Mutex<RoomSummary>>,
+) {
+    println!("[Room] game_id={} started", room.game_id);
+    loop {
+        let wakeup = room.next_wakeup();
+        tokio::select! {
+            command = commands.recv() => match command {
+                Some(command) => handle_command(&mut room, command),
+                None => break,
+            },
+            _ = sleep_for(wakeup) => check_timers(&mut room),
+        }
+        *summary.lock().unwrap() = RoomSummary {
+            game: room.summary(),
+            last_activity: Instant::now(),
+        };
+    }
+    println!("[Room] game_id={} closed", room.game_id);
+}
+
+async fn sleep_for(wakeup: Option<Duration>) {
+    match wakeup {
+        Some(duration) => tokio::time::sleep(duration).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Adjudicates flag falls, missed correspondence deadlines and players who
+/// did not come back in time.
+fn check_timers(room: &mut GameRoom) {
+    if let Some(game_state) = room.check_flag() {
+        broadcast_game_state(room, game_state);
+    }
+    if let Some(game_state) = room.check_abandoned() {
+        broadcast_game_state(room, game_state);
+    }
+}
+
+fn handle_command(room: &mut GameRoom, command: RoomCommand) {
+    match command {
+        RoomCommand::Join { client, reply } => {
+            let outcome = join(room, client);
+            let _ = reply.send(outcome);
+        }
+        RoomCommand::Action { client_id, action } => {
+            if let Some(game_state) = room.check_flag() {
+                broadcast_game_state(room, game_state);
+            }
+            if let Err(e) = handle_game_action(room, &client_id, action) {
+                room.send_to_client(
+                    &client_id,
+                    &ServerMessage::Error {
+                        message: e.to_string(),
+                    },
+                );
+            }
+        }
+        RoomCommand::Leave { sender } => leave(room, &sender),
+        RoomCommand::Pgn { reply } => {
+            let _ = reply.send(room.pgn());
+        }
+    }
+}
+
+fn join(room: &mut GameRoom, client: Client) -> JoinOutcome {
+    let id = client.id;
+    let sender = client.sender.clone();
+    let outcome = room.add_client(client);
+    match outcome {
+        JoinOutcome::Seated(color) => {
+            room.send_to_client(&id, &ServerMessage::ColorAssigned { color });
+            if room.is_game_started() {
+                // Back in the seat that was held for them.
+                for other in room.clients.iter().filter(|c| c.id != id) {
+                    room.send_to_client(&other.id, &ServerMessage::PlayerReconnected { color });
+                }
+            }
+        }
+        JoinOutcome::Spectator => {
+            room.send_to_client(&id, &ServerMessage::JoinedAsSpectator);
+            room.broadcast(&ServerMessage::SpectatorCount {
+                count: room.get_spectator_count(),
+            });
+        }
+        JoinOutcome::Replaced(Some(color)) | JoinOutcome::AlreadyJoined(Some(color)) => {
+            room.send_to_client(&id, &ServerMessage::ColorAssigned { color });
+        }
+        JoinOutcome::Replaced(None) | JoinOutcome::AlreadyJoined(None) => {
+            room.send_to_client(&id, &ServerMessage::JoinedAsSpectator);
+        }
+        JoinOutcome::RoomFull => {
+            let json = serde_json::to_string(&ServerMessage::RoomFull).unwrap();
+            let _ = sender.send(Message::Text(json.into()));
+            return outcome;
+        }
+    }
+
+    if room.can_start_game() {
+        room.start_game();
+        room.broadcast(&ServerMessage::GameStarted);
+        if let Some(state) = room.get_game_state() {
+            room.broadcast(&ServerMessage::game_state(state));
+        }
+    } else if room.is_game_started() {
+        if let Some(state) = room.get_game_state() {
+            room.send_to_client(&id, &ServerMessage::game_state(state));
+        }
+    } else {
+        room.broadcast(&ServerMessage::WaitingForPlayers {
+            connected_count: room.get_client_count(),
+            spectator_count: room.get_spectator_count(),
+        });
+    }
+    // A player coming back may find their opponent gone for good.
+    if let JoinOutcome::Seated(_) = outcome {
+        if let Some(game_state) = room.check_abandoned() {
+            broadcast_game_state(room, game_state);
+        }
+    }
+    outcome
+}
+
+fn leave(room: &mut GameRoom, sender: &mpsc::UnboundedSender<Message>) {
+    let players = room.get_client_count();
+    let spectators = room.get_spectator_count();
+    match room.remove_client(sender) {
+        Some((color, grace)) => {
+            room.broadcast(&ServerMessage::PlayerDisconnected {
+                color,
+                reconnect_within_ms: grace.map(|grace| grace.as_millis() as u64),
+            });
+        }
+        None if room.get_spectator_count() != spectators => {
+            room.broadcast(&ServerMessage::SpectatorCount {
+                count: room.get_spectator_count(),
+            });
+        }
+        None if room.get_client_count() != players && !room.is_game_started() => {
+            room.broadcast(&ServerMessage::WaitingForPlayers {
+                connected_count: room.get_client_count(),
+                spectator_count: room.get_spectator_count(),
+            });
+        }
+        None => {}
+    }
+}
+
+/// Applies an in-game action from a client in `room` and broadcasts the
+/// resulting updates to everyone in the room.
+fn handle_game_action(
+    room: &mut GameRoom,
+    client_id: &Uuid,
+    action: RoomAction,
+) -> Result<(), RoomError> {
+    match action {
+        RoomAction::MakeMove(move_) => {
+            let (move_, san, game_state) = room.handle_move(client_id, &move_)?;
+            let uci = move_.to_uci();
+            room.broadcast(&ServerMessage::MoveMade { move_, san, uci });
+            broadcast_game_state(room, game_state);
+        }
+        RoomAction::Resign => {
+            let game_state = room.resign(client_id)?;
+            broadcast_game_state(room, game_state);
+        }
+        RoomAction::ClaimDraw => {
+            let game_state = room.claim_draw(client_id)?;
+            broadcast_game_state(room, game_state);
+        }
+        RoomAction::OfferDraw => {
+            let by = room.offer_draw(client_id)?;
+            room.broadcast(&ServerMessage::DrawOffered { by });
+        }
+        RoomAction::AcceptDraw => {
+            let game_state = room.accept_draw(client_id)?;
+            broadcast_game_state(room, game_state);
+        }
+        RoomAction::DeclineDraw => {
+            let by = room.decline_draw(client_id)?;
+            room.broadcast(&ServerMessage::DrawDeclined { by });
+        }
+        RoomAction::RequestTakeback => {
+            let by = room.request_takeback(client_id)?;
+            room.broadcast(&ServerMessage::TakebackRequested { by });
+        }
+        RoomAction::AcceptTakeback => {
+            let (by, game_state) = room.accept_takeback(client_id)?;
+            room.broadcast(&ServerMessage::TakebackAccepted { by });
+            broadcast_game_state(room, game_state);
+        }
+        RoomAction::DeclineTakeback => {
+            let by = room.decline_takeback(client_id)?;
+            room.broadcast(&ServerMessage::TakebackDeclined { by });
+        }
+    }
+    Ok(())
+}
+
+fn broadcast_game_state(room: &GameRoom, game_state: GameState) {
+    room.broadcast(&ServerMessage::game_state(game_state));
+    if let Some(game_over) = room.game_over_message() {
+        room.broadcast(&game_over);
+    }
+}
@@ -1,22 +1,206 @@
-use crate::create_board::create_initial_board;
+use crate::room::RoomHandle;
 use axum::extract::ws::Message;
+use chessica_protocol::clock::{Clock, Correspondence, TimeControl};
+use chessica_protocol::pgn::{self, PgnTags};
+use chessica_protocol::rules::{self, MoveError, Undo};
 use chessica_protocol::types::*;
 use std::collections::HashMap;
+use std::fmt;
 use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 
 pub struct AppState {
-    pub game_rooms: Mutex<HashMap<Uuid, GameRoom>>,
-    pub client_rooms: Mutex<HashMap<Uuid, Uuid>>,
+    /// Handles to the rooms' tasks. Only held long enough to look a room up.
+    pub game_rooms: Mutex<HashMap<Uuid, RoomHandle>>,
+    pub room_ttls: RoomTtls,
+}
+
+impl AppState {
+    pub fn room(&self, game_id: &Uuid) -> Option<RoomHandle> {
+        self.game_rooms.lock().unwrap().get(game_id).cloned()
+    }
+}
+
+/// How long a room may sit idle with nobody connected before the reaper
+/// removes it, by status.
+#[derive(Debug, Clone, Copy)]
+pub struct RoomTtls {
+    /// Rooms still waiting for an opponent.
+    pub unjoined: Duration,
+    /// Games in progress that both players left without finishing.
+    pub abandoned: Duration,
+    pub finished: Duration,
+}
+
+impl RoomTtls {
+    /// The defaults, overridden by `CHESSICA_UNJOINED_ROOM_TTL_SECS`,
+    /// `CHESSICA_ABANDONED_ROOM_TTL_SECS` and `CHESSICA_FINISHED_ROOM_TTL_SECS`.
+    pub fn from_env() -> Self {
+        let secs = |name: &str, default: u64| {
+            let secs = match std::env::var(name) {
+                Ok(value) => value.parse().unwrap_or_else(|_| {
+                    eprintln!("Ignoring {}={:?}, expected whole seconds", name, value);
+                    default
+                }),
+                Err(_) => default,
+            };
+            Duration::from_secs(secs)
+        };
+        Self {
+            unjoined: secs("CHESSICA_UNJOINED_ROOM_TTL_SECS", 60 * 60),
+            abandoned: secs("CHESSICA_ABANDONED_ROOM_TTL_SECS", 24 * 60 * 60),
+            finished: secs("CHESSICA_FINISHED_ROOM_TTL_SECS", 60 * 60),
+        }
+    }
+
+    /// Idle time after which a room like `summary` expires. Correspondence
+    /// games in progress never do; their deadlines end them instead.
+    pub fn ttl(&self, summary: &GameSummary) -> Option<Duration> {
+        match summary.status {
+            RoomStatus::WaitingForOpponent => Some(self.unjoined),
+            RoomStatus::InProgress if summary.correspondence.is_some() => None,
+            RoomStatus::InProgress => Some(self.abandoned),
+            RoomStatus::Finished => Some(self.finished),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct Client {
     pub id: Uuid,
+    /// `None` for spectators.
     pub color: Option<Color>,
     pub sender: tokio::sync::mpsc::UnboundedSender<Message>,
 }
 
+/// Plies that must pass before a player may offer another draw.
+const DRAW_OFFER_COOLDOWN_PLIES: usize = 4;
+
+/// Spectators a room takes before turning clients away. Counts go out as a
+/// `u8`, so this stays below 256.
+const MAX_SPECTATORS: u8 = 200;
+
+/// How long a player who drops out of a game in progress keeps their seat.
+const RECONNECT_GRACE: Duration = Duration::from_secs(60);
+
+/// What happened to a client that asked to join a room.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinOutcome {
+    /// The client took a free seat, or came back to the one held for them.
+    Seated(Color),
+    Spectator,
+    /// Both seats and every spectator place are taken; the client was not
+    /// added.
+    RoomFull,
+    /// The client was already connected, and the new socket replaced the old
+    /// one. Carries their color, `None` for spectators.
+    Replaced(Option<Color>),
+    /// The client asked again on the socket they are already in the room on.
+    AlreadyJoined(Option<Color>),
+}
+
+#[derive(Debug)]
+pub enum RoomError {
+    NoGame,
+    GameOver,
+    NotInRoom,
+    NoColor,
+    NotYourTurn,
+    Illegal(MoveError),
+    NoDrawToClaim,
+    DrawAlreadyOffered,
+    DrawOfferTooSoon,
+    NoDrawOffer,
+    TakebackAlreadyRequested,
+    NothingToTakeBack,
+    NoTakebackRequest,
+}
+
+impl fmt::Display for RoomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoomError::NoGame => write!(f, "The game has not started"),
+            RoomError::GameOver => write!(f, "The game is over"),
+            RoomError::NotInRoom => write!(f, "You are not in this game"),
+            RoomError::NoColor => write!(f, "Spectators cannot play in this game"),
+            RoomError::NotYourTurn => write!(f, "It is not your turn"),
+            RoomError::Illegal(e) => write!(f, "Illegal move: {}", e),
+            RoomError::NoDrawToClaim => write!(f, "There is no draw to claim"),
+            RoomError::DrawAlreadyOffered => write!(f, "A draw offer is already pending"),
+            RoomError::DrawOfferTooSoon => write!(
+                f,
+                "You can only offer a draw once every {} moves",
+                DRAW_OFFER_COOLDOWN_PLIES / 2
+            ),
+            RoomError::NoDrawOffer => write!(f, "Your opponent has not offered a draw"),
+            RoomError::TakebackAlreadyRequested => write!(f, "A takeback is already pending"),
+            RoomError::NothingToTakeBack => write!(f, "You have no move to take back"),
+            RoomError::NoTakebackRequest => {
+                write!(f, "Your opponent has not requested a takeback")
+            }
+        }
+    }
+}
+
+/// Plies to rewind so that `requester` is to move again: just their last move
+/// if the opponent has not replied yet, otherwise the reply as well.
+fn takeback_plies(requester: Color, turn: Color) -> usize {
+    if turn == requester {
+        2
+    } else {
+        1
+    }
+}
+
+/// Milliseconds since the current turn started, 0 if no turn is running.
+fn elapsed_ms(turn_started: Option<Instant>) -> u64 {
+    turn_started.map_or(0, |started| started.elapsed().as_millis() as u64)
+}
+
+/// Stops the clock of a game that has just ended, charging the side to move
+/// for the time it used, and drops any move deadline.
+fn stop_clock(game_state: &mut GameState, turn_started: Option<Instant>) {
+    if let Some(clock) = &mut game_state.clock {
+        clock.stop(elapsed_ms(turn_started));
+    }
+    game_state.move_deadline = None;
+}
+
+fn unix_ms(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as u64)
+}
+
+/// The deadline for the move that is now due in a correspondence game, none
+/// once the game is over.
+fn next_deadline(correspondence: Option<Correspondence>, game_state: &GameState) -> Option<u64> {
+    if game_state.result.is_some() {
+        return None;
+    }
+    let time_per_move = correspondence?.time_per_move_ms();
+    Some(unix_ms(SystemTime::now()) + time_per_move)
+}
+
+/// Formats `time` as a PGN date (`YYYY.MM.DD`, UTC).
+fn pgn_date(time: SystemTime) -> String {
+    let Ok(elapsed) = time.duration_since(UNIX_EPOCH) else {
+        return "????.??.??".to_string();
+    };
+    // Civil-from-days conversion from Howard Hinnant's date algorithms.
+    let days = (elapsed.as_secs() / 86_400) as i64 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}.{:02}.{:02}", year, month, day)
+}
+
 #[derive(Debug)]
 pub struct GameRoom {
     pub clients: Vec<Client>,
@@ -24,63 +208,248 @@ pub struct GameRoom {
     pub game_started: bool,
     pub game_id: Uuid,
     pub reserved_color: Color,
+    pub initial_state: GameState,
+    /// Moves played from `initial_state` before the room opened, e.g. from an
+    /// imported PGN. Every game in the room resumes after them.
+    pub imported_moves: Vec<Move>,
+    pub created_at: SystemTime,
+    pub draw_offer: Option<Color>,
+    pub last_draw_offers: HashMap<Color, usize>,
+    pub takeback_request: Option<Color>,
+    pub undo_history: Vec<Undo>,
+    pub time_control: Option<TimeControl>,
+    /// When the side to move's clock started running, on the server's
+    /// monotonic clock.
+    pub turn_started: Option<Instant>,
+    pub correspondence: Option<Correspondence>,
+    /// Which client holds each color. Once the game has started, seats
+    /// outlive the socket so players can come back to them.
+    pub seats: HashMap<Color, Uuid>,
+    /// Seated players whose socket dropped, and when. Entries outlive the
+    /// grace period until the player comes back or the game is decided.
+    pub disconnected: HashMap<Color, Instant>,
 }
 
 impl GameRoom {
-    pub fn new(color: Color) -> Self {
+    pub fn new(
+        color: Color,
+        initial_state: GameState,
+        imported_moves: Vec<Move>,
+        time_control: Option<TimeControl>,
+        correspondence: Option<Correspondence>,
+    ) -> Self {
         Self {
             clients: Vec::new(),
             game_state: None,
             game_started: false,
             game_id: Uuid::new_v4(),
             reserved_color: color,
+            initial_state,
+            imported_moves,
+            created_at: SystemTime::now(),
+            draw_offer: None,
+            last_draw_offers: HashMap::new(),
+            takeback_request: None,
+            undo_history: Vec::new(),
+            time_control,
+            turn_started: None,
+            correspondence,
+            seats: HashMap::new(),
+            disconnected: HashMap::new(),
         }
     }
 
-    pub fn add_client(&mut self, client: Client) {
-        let color = if self.clients.is_empty() {
-            // First client gets the reserved color
-            self.reserved_color
-        } else if self.clients.len() == 1 {
-            // Second client gets the opposite color
-            match self.reserved_color {
-                Color::White => Color::Black,
-                Color::Black => Color::White,
+    /// Seats `client`, or adds them as a spectator once both seats are taken.
+    pub fn add_client(&mut self, client: Client) -> JoinOutcome {
+        if let Some(index) = self.clients.iter().position(|c| c.id == client.id) {
+            if self.clients[index].sender.same_channel(&client.sender) {
+                return JoinOutcome::AlreadyJoined(self.clients[index].color);
             }
-        } else {
-            // Room full, do not add more clients
-            return;
+            let old = self.clients.remove(index);
+            println!(
+                "[GameRoom] client_id={} connected again, closing the old socket",
+                client.id
+            );
+            let _ = old.sender.send(Message::Close(None));
+            self.clients.push(Client {
+                color: old.color,
+                ..client
+            });
+            return JoinOutcome::Replaced(old.color);
+        }
+
+        let seat = self
+            .seats
+            .iter()
+            .find(|(_, id)| **id == client.id)
+            .map(|(color, _)| *color);
+        let color = match seat {
+            Some(color) => {
+                println!("[GameRoom] {:?} is back: client_id={}", color, client.id);
+                self.disconnected.remove(&color);
+                color
+            }
+            // First client gets the reserved color, second the opposite one
+            None => match [self.reserved_color, self.reserved_color.opposite()]
+                .into_iter()
+                .find(|color| !self.seats.contains_key(color))
+            {
+                Some(color) => color,
+                None if self.get_spectator_count() < MAX_SPECTATORS => {
+                    println!(
+                        "[GameRoom] Room full, client_id={} joins as a spectator",
+                        client.id
+                    );
+                    self.clients.push(client);
+                    return JoinOutcome::Spectator;
+                }
+                None => {
+                    println!(
+                        "[GameRoom] No room left for client_id={} in game_id={}",
+                        client.id, self.game_id
+                    );
+                    return JoinOutcome::RoomFull;
+                }
+            },
         };
+        self.seats.insert(color, client.id);
+        self.clients.push(Client {
+            color: Some(color),
+            ..client
+        });
+        JoinOutcome::Seated(color)
+    }
 
-        let mut client_with_color = client;
-        client_with_color.color = Some(color);
+    /// Removes the client connected through `sender`. A player leaving a game
+    /// in progress keeps their seat; the color is returned with how long they
+    /// have to come back, `None` meaning as long as they like. Players of a
+    /// finished game keep their seats too, with nothing left to wait for.
+    pub fn remove_client(
+        &mut self,
+        sender: &tokio::sync::mpsc::UnboundedSender<Message>,
+    ) -> Option<(Color, Option<Duration>)> {
+        // Not found when the player already reconnected on another socket.
+        let index = self
+            .clients
+            .iter()
+            .position(|c| c.sender.same_channel(sender))?;
+        let client_id = &self.clients.remove(index).id;
+
+        let Some(color) = self
+            .seats
+            .iter()
+            .find(|(_, id)| *id == client_id)
+            .map(|(color, _)| *color)
+        else {
+            // Spectators hold no seat.
+            return None;
+        };
+        if self.game_state.as_ref().is_some_and(|s| s.result.is_some()) {
+            println!(
+                "[GameRoom] client_id={} left finished game_id={}",
+                client_id, self.game_id
+            );
+            return None;
+        }
+        if self.game_started {
+            if self.correspondence.is_some() {
+                println!(
+                    "[GameRoom] Keeping correspondence game_id={} for client_id={}",
+                    self.game_id, client_id
+                );
+                return Some((color, None));
+            }
+            println!(
+                "[GameRoom] Holding {:?} seat for client_id={} for {:?}",
+                color, client_id, RECONNECT_GRACE
+            );
+            self.disconnected.insert(color, Instant::now());
+            return Some((color, Some(RECONNECT_GRACE)));
+        }
 
-        self.clients.push(client_with_color);
+        self.seats.remove(&color);
+        if self.get_client_count() < 2 {
+            self.reset();
+        }
+        None
     }
 
-    pub fn remove_client(&mut self, client_id: &Uuid) {
-        self.clients.retain(|c| c.id != *client_id);
-        if self.clients.len() < 2 {
-            self.game_started = false;
-            self.game_state = None;
+    /// Ends the game by abandonment once a player's grace period has run out
+    /// and their opponent is connected to claim it. While both players are
+    /// away the game waits, seats held, so the first to come back wins it, or
+    /// the reaper removes the room.
+    pub fn check_abandoned(&mut self) -> Option<GameState> {
+        let game_state = self.game_state.as_mut()?;
+        if game_state.result.is_some() {
+            return None;
         }
+        let clients = &self.clients;
+        let connected = |color: Color| clients.iter().any(|c| c.color == Some(color));
+        let loser = self
+            .disconnected
+            .iter()
+            .filter(|(_, since)| since.elapsed() >= RECONNECT_GRACE)
+            .map(|(color, _)| *color)
+            .find(|color| connected(color.opposite()))?;
+
+        println!("[GameRoom] {:?} abandoned game_id={}", loser, self.game_id);
+        self.disconnected.remove(&loser);
+        game_state.result = Some(GameResult::win_for(loser.opposite()));
+        game_state.termination = Some(Termination::Abandonment);
+        stop_clock(game_state, self.turn_started);
+        self.turn_started = None;
+        Some(game_state.clone())
+    }
+
+    /// Drops the game and everything that goes with it.
+    fn reset(&mut self) {
+        self.game_started = false;
+        self.game_state = None;
+        self.draw_offer = None;
+        self.last_draw_offers.clear();
+        self.takeback_request = None;
+        self.undo_history.clear();
+        self.turn_started = None;
+        self.disconnected.clear();
     }
 
     pub fn can_start_game(&self) -> bool {
-        self.clients.len() == 2 && !self.game_started
+        self.get_client_count() == 2 && !self.game_started
     }
 
     pub fn start_game(&mut self) {
         if self.can_start_game() {
-            self.game_state = Some(GameState {
-                board: create_initial_board(),
-                turn: Color::White,
-                move_history: vec![],
-            });
+            let (mut game_state, undo_history) = self.opening_state();
+            if let Some(time_control) = self.time_control {
+                if game_state.result.is_none() {
+                    game_state.clock = Some(Clock::new(time_control, game_state.position.turn));
+                    self.turn_started = Some(Instant::now());
+                }
+            }
+            game_state.move_deadline = next_deadline(self.correspondence, &game_state);
+            self.game_state = Some(game_state);
+            self.undo_history = undo_history;
             self.game_started = true;
         }
     }
 
+    /// The position after `imported_moves`, with the undo records that let
+    /// players take them back.
+    fn opening_state(&self) -> (GameState, Vec<Undo>) {
+        let mut game_state = self.initial_state.clone();
+        let mut undo_history = Vec::with_capacity(self.imported_moves.len());
+        for move_ in &self.imported_moves {
+            match rules::apply_move(&mut game_state, move_) {
+                Ok(undo) => undo_history.push(undo),
+                Err(e) => {
+                    eprintln!("[GameRoom] Imported move {:?} rejected: {}", move_, e);
+                    break;
+                }
+            }
+        }
+        (game_state, undo_history)
+    }
+
     pub fn broadcast(&self, message: &ServerMessage) {
         let json = serde_json::to_string(message).unwrap();
         let ws_message = Message::Text(json.clone().into());
@@ -117,8 +486,13 @@ impl GameRoom {
         }
     }
 
+    /// Number of players connected, not counting spectators.
     pub fn get_client_count(&self) -> u8 {
-        self.clients.len() as u8
+        self.clients.iter().filter(|c| c.color.is_some()).count() as u8
+    }
+
+    pub fn get_spectator_count(&self) -> u8 {
+        self.clients.iter().filter(|c| c.color.is_none()).count() as u8
     }
 
     pub fn is_game_started(&self) -> bool {
@@ -129,43 +503,507 @@ impl GameRoom {
         self.game_state.clone()
     }
 
-    pub fn handle_move(&mut self, client_id: &Uuid, move_: &Move) -> Option<(Move, GameState)> {
+    pub fn pgn(&self) -> String {
+        let tags = PgnTags {
+            event: "Chessica game".to_string(),
+            site: "Chessica".to_string(),
+            date: pgn_date(self.created_at),
+            round: "-".to_string(),
+            ..PgnTags::default()
+        };
+        match &self.game_state {
+            Some(state) => pgn::to_pgn(&self.initial_state, state, &tags),
+            None => pgn::to_pgn(&self.initial_state, &self.opening_state().0, &tags),
+        }
+    }
+
+    /// The room as it appears in the `GET /api/games` listing.
+    pub fn summary(&self) -> GameSummary {
+        let game_state = self.game_state.as_ref();
+        let status = match game_state {
+            None => RoomStatus::WaitingForOpponent,
+            Some(state) if state.result.is_some() => RoomStatus::Finished,
+            Some(_) => RoomStatus::InProgress,
+        };
+        GameSummary {
+            game_id: self.game_id,
+            status,
+            players: self.get_client_count(),
+            spectators: self.get_spectator_count(),
+            open_color: [self.reserved_color, self.reserved_color.opposite()]
+                .into_iter()
+                .find(|color| !self.seats.contains_key(color)),
+            time_control: self.time_control,
+            correspondence: self.correspondence,
+            result: game_state.and_then(|state| state.result),
+            plies: game_state.map_or(0, |state| state.move_history.len()),
+            created_at: unix_ms(self.created_at),
+        }
+    }
+
+    pub fn game_over_message(&self) -> Option<ServerMessage> {
+        let state = self.game_state.as_ref()?;
+        Some(ServerMessage::GameOver {
+            result: state.result?,
+            reason: state.termination?,
+        })
+    }
+
+    /// Time until a flag may fall, a correspondence deadline passes or a
+    /// disconnected player's grace period ends, whichever comes first.
+    pub fn next_wakeup(&self) -> Option<Duration> {
+        let game_state = self.game_state.as_ref();
+        let flag = game_state
+            .and_then(|state| state.clock)
+            .and_then(|clock| clock.time_to_flag(elapsed_ms(self.turn_started)));
+        let deadline = game_state
+            .and_then(|state| state.move_deadline)
+            .map(|deadline| deadline.saturating_sub(unix_ms(SystemTime::now())));
+        // Expired grace periods wait for the opponent to come back instead.
+        let grace = self
+            .disconnected
+            .values()
+            .map(|since| since.elapsed())
+            .filter(|&elapsed| elapsed < RECONNECT_GRACE)
+            .map(|elapsed| RECONNECT_GRACE - elapsed);
+        flag.into_iter()
+            .chain(deadline)
+            .map(Duration::from_millis)
+            .chain(grace)
+            .min()
+    }
+
+    /// Ends the game if the side to move has run out of time or missed its
+    /// correspondence deadline. The opponent wins unless they could not have
+    /// mated, in which case it is a draw.
+    pub fn check_flag(&mut self) -> Option<GameState> {
+        let game_state = self.game_state.as_mut()?;
+        if game_state.result.is_some() {
+            return None;
+        }
+        let flagged = game_state
+            .clock
+            .is_some_and(|clock| clock.flagged(elapsed_ms(self.turn_started)));
+        let overdue = game_state
+            .move_deadline
+            .is_some_and(|deadline| unix_ms(SystemTime::now()) >= deadline);
+        if !flagged && !overdue {
+            return None;
+        }
+        stop_clock(game_state, self.turn_started);
+        let loser = game_state.position.turn;
         println!(
-            "[GameRoom] handle_move called by client_id={:?} with move: from {} to {}",
-            client_id, move_.from, move_.to
+            "[GameRoom] {:?} ran out of time in game_id={}",
+            loser, self.game_id
         );
-        if let Some(ref mut game_state) = self.game_state {
-            if let Some(client) = self.clients.iter().find(|c| c.id == *client_id) {
-                if let Some(client_color) = client.color {
-                    if game_state.turn == client_color {
-                        // TODO: validate move via chess lib
-                        game_state.board[move_.to as usize] =
-                            game_state.board[move_.from as usize].take();
-                        game_state.turn = match game_state.turn {
-                            Color::White => Color::Black,
-                            Color::Black => Color::White,
-                        };
-                        game_state.move_history.push(move_.clone());
-                        println!("[GameRoom] Move applied. Next turn: {:?}", game_state.turn);
-                        Some((move_.clone(), game_state.clone()))
-                    } else {
-                        println!(
-                            "[GameRoom] Not this client's turn: client_color={:?} turn={:?}",
-                            client_color, game_state.turn
-                        );
-                        None
-                    }
-                } else {
-                    println!("[GameRoom] Client has no color assigned");
-                    None
-                }
-            } else {
-                println!("[GameRoom] Client not found in room");
-                None
-            }
+        if rules::has_mating_material(&game_state.position.board, loser.opposite()) {
+            game_state.result = Some(GameResult::win_for(loser.opposite()));
+            game_state.termination = Some(Termination::Timeout);
         } else {
+            game_state.result = Some(GameResult::Draw);
+            game_state.termination = Some(Termination::TimeoutVsInsufficientMaterial);
+        }
+        self.turn_started = None;
+        Some(game_state.clone())
+    }
+
+    /// Returns the color of a seated player together with the game in progress.
+    /// Takes the room's fields separately so callers can keep using the rest
+    /// of the room while holding the game state.
+    fn player_game<'a>(
+        game_state: &'a mut Option<GameState>,
+        clients: &[Client],
+        client_id: &Uuid,
+    ) -> Result<(Color, &'a mut GameState), RoomError> {
+        let game_state = game_state.as_mut().ok_or(RoomError::NoGame)?;
+        if game_state.result.is_some() {
+            return Err(RoomError::GameOver);
+        }
+        let client = clients
+            .iter()
+            .find(|c| c.id == *client_id)
+            .ok_or(RoomError::NotInRoom)?;
+        let color = client.color.ok_or(RoomError::NoColor)?;
+        Ok((color, game_state))
+    }
+
+    pub fn resign(&mut self, client_id: &Uuid) -> Result<GameState, RoomError> {
+        let (color, game_state) =
+            Self::player_game(&mut self.game_state, &self.clients, client_id)?;
+        println!("[GameRoom] {:?} resigned: client_id={}", color, client_id);
+        game_state.result = Some(GameResult::win_for(color.opposite()));
+        game_state.termination = Some(Termination::Resignation);
+        stop_clock(game_state, self.turn_started);
+        Ok(game_state.clone())
+    }
+
+    pub fn offer_draw(&mut self, client_id: &Uuid) -> Result<Color, RoomError> {
+        let (color, game_state) =
+            Self::player_game(&mut self.game_state, &self.clients, client_id)?;
+        let ply = game_state.move_history.len();
+        if self.draw_offer.is_some() {
+            return Err(RoomError::DrawAlreadyOffered);
+        }
+        if self
+            .last_draw_offers
+            .get(&color)
+            .is_some_and(|&last| ply < last + DRAW_OFFER_COOLDOWN_PLIES)
+        {
+            return Err(RoomError::DrawOfferTooSoon);
+        }
+
+        println!("[GameRoom] {:?} offered a draw", color);
+        self.draw_offer = Some(color);
+        self.last_draw_offers.insert(color, ply);
+        Ok(color)
+    }
+
+    pub fn accept_draw(&mut self, client_id: &Uuid) -> Result<GameState, RoomError> {
+        let offer = self.draw_offer;
+        let (color, game_state) =
+            Self::player_game(&mut self.game_state, &self.clients, client_id)?;
+        if offer != Some(color.opposite()) {
+            return Err(RoomError::NoDrawOffer);
+        }
+
+        println!("[GameRoom] {:?} accepted the draw offer", color);
+        game_state.result = Some(GameResult::Draw);
+        game_state.termination = Some(Termination::Agreement);
+        stop_clock(game_state, self.turn_started);
+        let game_state = game_state.clone();
+        self.draw_offer = None;
+        Ok(game_state)
+    }
+
+    pub fn decline_draw(&mut self, client_id: &Uuid) -> Result<Color, RoomError> {
+        let offer = self.draw_offer;
+        let (color, _) = Self::player_game(&mut self.game_state, &self.clients, client_id)?;
+        if offer != Some(color.opposite()) {
+            return Err(RoomError::NoDrawOffer);
+        }
+
+        println!("[GameRoom] {:?} declined the draw offer", color);
+        self.draw_offer = None;
+        Ok(color)
+    }
+
+    pub fn request_takeback(&mut self, client_id: &Uuid) -> Result<Color, RoomError> {
+        let (color, game_state) =
+            Self::player_game(&mut self.game_state, &self.clients, client_id)?;
+        if self.takeback_request.is_some() {
+            return Err(RoomError::TakebackAlreadyRequested);
+        }
+        if self.undo_history.len() < takeback_plies(color, game_state.position.turn) {
+            return Err(RoomError::NothingToTakeBack);
+        }
+
+        println!("[GameRoom] {:?} requested a takeback", color);
+        self.takeback_request = Some(color);
+        Ok(color)
+    }
+
+    pub fn accept_takeback(&mut self, client_id: &Uuid) -> Result<(Color, GameState), RoomError> {
+        let request = self.takeback_request;
+        let (color, game_state) =
+            Self::player_game(&mut self.game_state, &self.clients, client_id)?;
+        let Some(requester) = request.filter(|&r| r == color.opposite()) else {
+            return Err(RoomError::NoTakebackRequest);
+        };
+
+        let plies = takeback_plies(requester, game_state.position.turn);
+        for _ in 0..plies {
+            let Some(undo) = self.undo_history.pop() else {
+                break;
+            };
+            rules::undo_move(game_state, &undo);
+        }
+        // The time used so far stays used; the clock restarts for whoever is
+        // to move after the takeback.
+        if let Some(clock) = &mut game_state.clock {
+            clock.stop(elapsed_ms(self.turn_started));
+            clock.running = Some(game_state.position.turn);
+            self.turn_started = Some(Instant::now());
+        }
+        game_state.move_deadline = next_deadline(self.correspondence, game_state);
+        println!(
+            "[GameRoom] {:?} accepted a takeback of {} plies",
+            color, plies
+        );
+        let game_state = game_state.clone();
+        self.takeback_request = None;
+        if let Some(by) = self.draw_offer.take() {
+            self.broadcast(&ServerMessage::DrawOfferCancelled { by });
+        }
+        Ok((color, game_state))
+    }
+
+    pub fn decline_takeback(&mut self, client_id: &Uuid) -> Result<Color, RoomError> {
+        let request = self.takeback_request;
+        let (color, _) = Self::player_game(&mut self.game_state, &self.clients, client_id)?;
+        if request != Some(color.opposite()) {
+            return Err(RoomError::NoTakebackRequest);
+        }
+
+        println!("[GameRoom] {:?} declined the takeback", color);
+        self.takeback_request = None;
+        Ok(color)
+    }
+
+    pub fn claim_draw(&mut self, client_id: &Uuid) -> Result<GameState, RoomError> {
+        let (_, game_state) = Self::player_game(&mut self.game_state, &self.clients, client_id)?;
+        let Some(termination) = rules::claimable_draw(game_state) else {
+            return Err(RoomError::NoDrawToClaim);
+        };
+        println!(
+            "[GameRoom] Draw claimed by client_id={} ({:?})",
+            client_id, termination
+        );
+        game_state.result = Some(GameResult::Draw);
+        game_state.termination = Some(termination);
+        stop_clock(game_state, self.turn_started);
+        Ok(game_state.clone())
+    }
+
+    pub fn handle_move(
+        &mut self,
+        client_id: &Uuid,
+        move_: &Move,
+    ) -> Result<(Move, String, GameState), RoomError> {
+        println!(
+            "[GameRoom] handle_move called by client_id={:?} with move: {}",
+            client_id,
+            move_.to_uci()
+        );
+        let Some(ref mut game_state) = self.game_state else {
             println!("[GameRoom] No game state");
-            None
+            return Err(RoomError::NoGame);
+        };
+        if game_state.result.is_some() {
+            println!("[GameRoom] Game is already over");
+            return Err(RoomError::GameOver);
+        }
+        let Some(client) = self.clients.iter().find(|c| c.id == *client_id) else {
+            println!("[GameRoom] Client not found in room");
+            return Err(RoomError::NotInRoom);
+        };
+        let Some(client_color) = client.color else {
+            println!("[GameRoom] Client has no color assigned");
+            return Err(RoomError::NoColor);
+        };
+        if game_state.position.turn != client_color {
+            println!(
+                "[GameRoom] Not this client's turn: client_color={:?} turn={:?}",
+                client_color, game_state.position.turn
+            );
+            return Err(RoomError::NotYourTurn);
         }
+
+        let san = move_.to_san(game_state);
+        let undo = match rules::apply_move(game_state, move_) {
+            Ok(undo) => undo,
+            Err(e) => {
+                println!("[GameRoom] Illegal move rejected: {}", e);
+                return Err(RoomError::Illegal(e));
+            }
+        };
+        self.undo_history.push(undo);
+        if let Some(clock) = &mut game_state.clock {
+            let elapsed = elapsed_ms(self.turn_started);
+            if game_state.result.is_some() {
+                clock.stop(elapsed);
+            } else {
+                clock.press(elapsed);
+            }
+            self.turn_started = Some(Instant::now());
+        }
+        game_state.move_deadline = next_deadline(self.correspondence, game_state);
+        println!(
+            "[GameRoom] Move {} applied. Next turn: {:?}, position {}",
+            san, game_state.position.turn, game_state.position.zobrist
+        );
+        let game_state = game_state.clone();
+        match self.draw_offer.take() {
+            Some(by) if by == client_color => {
+                println!("[GameRoom] Draw offer by {:?} cancelled", by);
+                self.broadcast(&ServerMessage::DrawOfferCancelled { by });
+            }
+            // Moving instead of accepting declines the offer.
+            Some(by) => {
+                println!("[GameRoom] Draw offer by {:?} declined by moving", by);
+                self.broadcast(&ServerMessage::DrawDeclined { by: client_color });
+            }
+            None => {}
+        }
+        if let Some(by) = self.takeback_request.take() {
+            println!("[GameRoom] Takeback request by {:?} cancelled", by);
+            self.broadcast(&ServerMessage::TakebackCancelled { by });
+        }
+        if let Some(termination) = game_state.termination {
+            println!(
+                "[GameRoom] Game over: {:?} by {:?}",
+                game_state.result, termination
+            );
+        }
+        Ok((move_.clone(), san, game_state))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chessica_protocol::fen::STARTING_FEN;
+    use tokio::sync::mpsc;
+
+    struct Player {
+        id: Uuid,
+        sender: mpsc::UnboundedSender<Message>,
+        _receiver: mpsc::UnboundedReceiver<Message>,
+    }
+
+    fn connect(room: &mut GameRoom, id: Uuid) -> (Player, JoinOutcome) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let outcome = room.add_client(Client {
+            id,
+            color: None,
+            sender: sender.clone(),
+        });
+        let player = Player {
+            id,
+            sender,
+            _receiver: receiver,
+        };
+        (player, outcome)
+    }
+
+    /// A started untimed game with white and black connected.
+    fn started_room() -> (GameRoom, Player, Player) {
+        let initial_state = GameState::from_fen(STARTING_FEN).unwrap();
+        let mut room = GameRoom::new(Color::White, initial_state, vec![], None, None);
+        let (white, _) = connect(&mut room, Uuid::new_v4());
+        let (black, _) = connect(&mut room, Uuid::new_v4());
+        room.start_game();
+        assert!(room.is_game_started());
+        (room, white, black)
+    }
+
+    /// Disconnects `player`, as if their socket had dropped `secs_ago`.
+    fn drop_out(room: &mut GameRoom, player: &Player, secs_ago: u64) {
+        let (color, _) = room.remove_client(&player.sender).unwrap();
+        backdate(room, color, secs_ago);
+    }
+
+    fn backdate(room: &mut GameRoom, color: Color, secs_ago: u64) {
+        let since = Instant::now()
+            .checked_sub(Duration::from_secs(secs_ago))
+            .unwrap();
+        room.disconnected.insert(color, since);
+    }
+
+    fn outcome(room: &GameRoom) -> Option<(GameResult, Termination)> {
+        let game_state = room.game_state.as_ref().unwrap();
+        game_state.result.zip(game_state.termination)
+    }
+
+    #[test]
+    fn opponent_present_wins_once_grace_runs_out() {
+        let (mut room, white, _black) = started_room();
+        drop_out(&mut room, &white, 30);
+        assert!(room.check_abandoned().is_none());
+
+        backdate(&mut room, Color::White, 61);
+        assert!(room.check_abandoned().is_some());
+        assert_eq!(
+            outcome(&room),
+            Some((GameResult::BlackWins, Termination::Abandonment))
+        );
+    }
+
+    /// `first` drops out, then their opponent, and `first`'s grace runs out
+    /// while the opponent's has not.
+    fn both_drop_out(first: Color) -> (GameRoom, Player, Player) {
+        let (mut room, white, black) = started_room();
+        let (leaves_first, leaves_second) = match first {
+            Color::White => (&white, &black),
+            Color::Black => (&black, &white),
+        };
+        drop_out(&mut room, leaves_first, 61);
+        drop_out(&mut room, leaves_second, 31);
+
+        // Nobody is there to claim the win.
+        assert!(room.check_abandoned().is_none());
+        assert_eq!(outcome(&room), None);
+        // Only the second player's grace is still running.
+        let wakeup = room.next_wakeup().unwrap();
+        assert!(wakeup > Duration::from_secs(25) && wakeup <= Duration::from_secs(29));
+        (room, white, black)
+    }
+
+    #[test]
+    fn white_leaves_first_and_black_comes_back() {
+        let (mut room, _white, black) = both_drop_out(Color::White);
+        let (_black, joined) = connect(&mut room, black.id);
+        assert_eq!(joined, JoinOutcome::Seated(Color::Black));
+        assert!(room.check_abandoned().is_some());
+        assert_eq!(
+            outcome(&room),
+            Some((GameResult::BlackWins, Termination::Abandonment))
+        );
+    }
+
+    #[test]
+    fn black_leaves_first_and_white_comes_back() {
+        let (mut room, white, _black) = both_drop_out(Color::Black);
+        let (_white, joined) = connect(&mut room, white.id);
+        assert_eq!(joined, JoinOutcome::Seated(Color::White));
+        assert!(room.check_abandoned().is_some());
+        assert_eq!(
+            outcome(&room),
+            Some((GameResult::WhiteWins, Termination::Abandonment))
+        );
+    }
+
+    #[test]
+    fn first_back_wins_when_both_are_gone_for_good() {
+        let (mut room, white, black) = both_drop_out(Color::White);
+        backdate(&mut room, Color::Black, 61);
+        // Both are gone for good; the game waits without timers to run.
+        assert!(room.check_abandoned().is_none());
+        assert_eq!(room.next_wakeup(), None);
+
+        // The first player back claims the game.
+        let (_white, _) = connect(&mut room, white.id);
+        assert!(room.check_abandoned().is_some());
+        assert_eq!(
+            outcome(&room),
+            Some((GameResult::WhiteWins, Termination::Abandonment))
+        );
+        let (_black, joined) = connect(&mut room, black.id);
+        assert_eq!(joined, JoinOutcome::Seated(Color::Black));
+        assert!(room.check_abandoned().is_none());
+    }
+
+    #[test]
+    fn old_socket_leaving_keeps_the_reconnected_player() {
+        let (mut room, white, _black) = started_room();
+        let (_white_again, joined) = connect(&mut room, white.id);
+        assert_eq!(joined, JoinOutcome::Replaced(Some(Color::White)));
+        assert_eq!(room.remove_client(&white.sender), None);
+        assert!(room.disconnected.is_empty());
+        assert_eq!(room.get_client_count(), 2);
+    }
+
+    #[test]
+    fn leaving_a_finished_game_keeps_it() {
+        let (mut room, white, black) = started_room();
+        room.resign(&white.id).unwrap();
+        assert_eq!(room.remove_client(&white.sender), None);
+        assert_eq!(room.remove_client(&black.sender), None);
+        assert!(room.disconnected.is_empty());
+        assert!(room.check_abandoned().is_none());
+        assert_eq!(
+            outcome(&room),
+            Some((GameResult::BlackWins, Termination::Resignation))
+        );
+        assert_eq!(room.summary().status, RoomStatus::Finished);
     }
 }
@@ -0,0 +1,32 @@
+use chessica_protocol::fen::STARTING_FEN;
+use chessica_protocol::perft::perft;
+use chessica_protocol::types::*;
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::hint::black_box;
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+fn legal_moves(c: &mut Criterion) {
+    let start = Position::from_fen(STARTING_FEN).unwrap();
+    let kiwipete = Position::from_fen(KIWIPETE).unwrap();
+    c.bench_function("legal_moves startpos", |b| {
+        b.iter(|| black_box(&start).legal_moves())
+    });
+    c.bench_function("legal_moves kiwipete", |b| {
+        b.iter(|| black_box(&kiwipete).legal_moves())
+    });
+}
+
+fn perft_bench(c: &mut Criterion) {
+    let start = Position::from_fen(STARTING_FEN).unwrap();
+    let kiwipete = Position::from_fen(KIWIPETE).unwrap();
+    c.bench_function("perft startpos 3", |b| {
+        b.iter(|| perft(black_box(&start), 3))
+    });
+    c.bench_function("perft kiwipete 2", |b| {
+        b.iter(|| perft(black_box(&kiwipete), 2))
+    });
+}
+
+criterion_group!(benches, legal_moves, perft_bench);
+criterion_main!(benches);
@@ -0,0 +1,33 @@
+//! Prints perft divide output for a position:
+//!
+//!     cargo run --release -p chessica-protocol --example perft -- "<fen>" <depth>
+
+use chessica_protocol::fen::STARTING_FEN;
+use chessica_protocol::perft::divide;
+use chessica_protocol::types::*;
+use std::time::Instant;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let fen = args.next().unwrap_or_else(|| STARTING_FEN.to_string());
+    let depth = args.next().and_then(|d| d.parse().ok()).unwrap_or(4);
+
+    let position = match Position::from_fen(&fen) {
+        Ok(position) => position,
+        Err(e) => {
+            eprintln!("Invalid FEN: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let started = Instant::now();
+    let split = divide(&position, depth);
+    let total: u64 = split.iter().map(|(_, nodes)| nodes).sum();
+    for (move_, nodes) in &split {
+        println!("{}: {}", move_.to_uci(), nodes);
+    }
+    println!();
+    println!("Moves: {}", split.len());
+    println!("Nodes: {}", total);
+    println!("Time: {:?}", started.elapsed());
+}
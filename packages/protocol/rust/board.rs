@@ -0,0 +1,299 @@
+use crate::types::*;
+use std::fmt;
+
+// Bit `i` of a bitboard is the square with index `i`, so a8 is bit 0 and h1
+// is bit 63, matching `Square::index`.
+
+/// A set of squares, one bit per square.
+pub type Bitboard = u64;
+
+const PIECE_TYPES: [PieceType; 6] = [
+    PieceType::Pawn,
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Rook,
+    PieceType::Queen,
+    PieceType::King,
+];
+
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (-2, -1),
+    (-2, 1),
+    (-1, -2),
+    (-1, 2),
+    (1, -2),
+    (1, 2),
+    (2, -1),
+    (2, 1),
+];
+
+const KING_OFFSETS: [(i8, i8); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+// White pawns advance towards row 0, black pawns towards row 7.
+const WHITE_PAWN_OFFSETS: [(i8, i8); 2] = [(-1, -1), (-1, 1)];
+const BLACK_PAWN_OFFSETS: [(i8, i8); 2] = [(1, -1), (1, 1)];
+
+const ROOK_DIRECTIONS: [(i8, i8); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+const BISHOP_DIRECTIONS: [(i8, i8); 4] = [(-1, -1), (-1, 1), (1, -1), (1, 1)];
+
+const fn on_board(row: i8, col: i8) -> bool {
+    row >= 0 && row < 8 && col >= 0 && col < 8
+}
+
+const fn leaper_table(offsets: &[(i8, i8)]) -> [Bitboard; 64] {
+    let mut table = [0; 64];
+    let mut square = 0;
+    while square < 64 {
+        let mut i = 0;
+        while i < offsets.len() {
+            let row = (square / 8) as i8 + offsets[i].0;
+            let col = (square % 8) as i8 + offsets[i].1;
+            if on_board(row, col) {
+                table[square] |= 1 << (row * 8 + col);
+            }
+            i += 1;
+        }
+        square += 1;
+    }
+    table
+}
+
+/// Every square from each square to the edge of the board in each direction,
+/// not including the square itself.
+const fn ray_table(directions: &[(i8, i8); 4]) -> [[Bitboard; 64]; 4] {
+    let mut table = [[0; 64]; 4];
+    let mut d = 0;
+    while d < 4 {
+        let mut square = 0;
+        while square < 64 {
+            let mut row = (square / 8) as i8 + directions[d].0;
+            let mut col = (square % 8) as i8 + directions[d].1;
+            while on_board(row, col) {
+                table[d][square] |= 1 << (row * 8 + col);
+                row += directions[d].0;
+                col += directions[d].1;
+            }
+            square += 1;
+        }
+        d += 1;
+    }
+    table
+}
+
+const KNIGHT_ATTACKS: [Bitboard; 64] = leaper_table(&KNIGHT_OFFSETS);
+const KING_ATTACKS: [Bitboard; 64] = leaper_table(&KING_OFFSETS);
+const WHITE_PAWN_ATTACKS: [Bitboard; 64] = leaper_table(&WHITE_PAWN_OFFSETS);
+const BLACK_PAWN_ATTACKS: [Bitboard; 64] = leaper_table(&BLACK_PAWN_OFFSETS);
+const ROOK_RAYS: [[Bitboard; 64]; 4] = ray_table(&ROOK_DIRECTIONS);
+const BISHOP_RAYS: [[Bitboard; 64]; 4] = ray_table(&BISHOP_DIRECTIONS);
+
+/// Squares of the same color as a8.
+pub const LIGHT_SQUARES: Bitboard = 0xaa55_aa55_aa55_aa55;
+
+pub fn square_bit(square: Square) -> Bitboard {
+    1 << square.index()
+}
+
+/// The squares in `bitboard`, lowest index first.
+pub fn squares(mut bitboard: Bitboard) -> impl Iterator<Item = Square> {
+    std::iter::from_fn(move || {
+        // `trailing_zeros` is 64 once the set is empty, which is no square.
+        let square = Square::new(bitboard.trailing_zeros() as u8)?;
+        bitboard &= bitboard - 1;
+        Some(square)
+    })
+}
+
+pub fn knight_attacks(square: Square) -> Bitboard {
+    KNIGHT_ATTACKS[square.index()]
+}
+
+pub fn king_attacks(square: Square) -> Bitboard {
+    KING_ATTACKS[square.index()]
+}
+
+/// Squares a pawn of `color` standing on `square` attacks.
+pub fn pawn_attacks(color: Color, square: Square) -> Bitboard {
+    match color {
+        Color::White => WHITE_PAWN_ATTACKS[square.index()],
+        Color::Black => BLACK_PAWN_ATTACKS[square.index()],
+    }
+}
+
+/// Slides along each ray up to and including the first occupied square.
+fn slider_attacks(
+    rays: &[[Bitboard; 64]; 4],
+    directions: &[(i8, i8); 4],
+    square: Square,
+    occupied: Bitboard,
+) -> Bitboard {
+    let mut attacks = 0;
+    for (ray, &(d_row, d_col)) in rays.iter().zip(directions) {
+        let reach = ray[square.index()];
+        let blockers = reach & occupied;
+        if blockers == 0 {
+            attacks |= reach;
+            continue;
+        }
+        // Rays towards higher indices meet their nearest blocker at the lowest
+        // set bit, rays towards lower indices at the highest.
+        let nearest = if d_row * 8 + d_col > 0 {
+            blockers.trailing_zeros()
+        } else {
+            63 - blockers.leading_zeros()
+        };
+        attacks |= reach & !ray[nearest as usize];
+    }
+    attacks
+}
+
+pub fn rook_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    slider_attacks(&ROOK_RAYS, &ROOK_DIRECTIONS, square, occupied)
+}
+
+pub fn bishop_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    slider_attacks(&BISHOP_RAYS, &BISHOP_DIRECTIONS, square, occupied)
+}
+
+pub fn queen_attacks(square: Square, occupied: Bitboard) -> Bitboard {
+    rook_attacks(square, occupied) | bishop_attacks(square, occupied)
+}
+
+pub(crate) fn color_index(color: Color) -> usize {
+    match color {
+        Color::White => 0,
+        Color::Black => 1,
+    }
+}
+
+pub(crate) fn piece_index(piece: PieceType) -> usize {
+    match piece {
+        PieceType::Pawn => 0,
+        PieceType::Knight => 1,
+        PieceType::Bishop => 2,
+        PieceType::Rook => 3,
+        PieceType::Queen => 4,
+        PieceType::King => 5,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BoardError {
+    WrongLength(usize),
+}
+
+impl fmt::Display for BoardError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoardError::WrongLength(length) => {
+                write!(f, "a board has 64 squares, found {}", length)
+            }
+        }
+    }
+}
+
+impl std::error::Error for BoardError {}
+
+/// Piece placement as one bitboard per color and one per piece type. On the
+/// wire it is the 64-entry array of `Option<Piece>` indexed by `Square`.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(into = "Vec<Option<Piece>>", try_from = "Vec<Option<Piece>>")]
+pub struct Board {
+    colors: [Bitboard; 2],
+    pieces: [Bitboard; 6],
+}
+
+impl Board {
+    pub fn piece_at(&self, square: Square) -> Option<Piece> {
+        let bit = square_bit(square);
+        let color = if self.colors[0] & bit != 0 {
+            Color::White
+        } else if self.colors[1] & bit != 0 {
+            Color::Black
+        } else {
+            return None;
+        };
+        let piece = PIECE_TYPES
+            .into_iter()
+            .find(|&piece| self.pieces[piece_index(piece)] & bit != 0)?;
+        Some(Piece { color, piece })
+    }
+
+    /// Puts `piece` on `square`, replacing whatever stood there.
+    pub fn set(&mut self, square: Square, piece: Option<Piece>) {
+        let bit = square_bit(square);
+        for bitboard in self.colors.iter_mut().chain(&mut self.pieces) {
+            *bitboard &= !bit;
+        }
+        if let Some(piece) = piece {
+            self.colors[color_index(piece.color)] |= bit;
+            self.pieces[piece_index(piece.piece)] |= bit;
+        }
+    }
+
+    /// Removes and returns the piece on `square`.
+    pub fn take(&mut self, square: Square) -> Option<Piece> {
+        let piece = self.piece_at(square);
+        if piece.is_some() {
+            self.set(square, None);
+        }
+        piece
+    }
+
+    pub fn occupied(&self) -> Bitboard {
+        self.colors[0] | self.colors[1]
+    }
+
+    pub fn by_color(&self, color: Color) -> Bitboard {
+        self.colors[color_index(color)]
+    }
+
+    pub fn by_type(&self, piece: PieceType) -> Bitboard {
+        self.pieces[piece_index(piece)]
+    }
+
+    pub fn pieces(&self, color: Color, piece: PieceType) -> Bitboard {
+        self.by_color(color) & self.by_type(piece)
+    }
+
+    pub fn king_square(&self, color: Color) -> Option<Square> {
+        squares(self.pieces(color, PieceType::King)).next()
+    }
+
+    /// Every piece on the board with its square, in square order.
+    pub fn iter(&self) -> impl Iterator<Item = (Square, Piece)> + '_ {
+        squares(self.occupied()).filter_map(|square| Some((square, self.piece_at(square)?)))
+    }
+}
+
+impl From<Board> for Vec<Option<Piece>> {
+    fn from(board: Board) -> Self {
+        Square::all().map(|square| board.piece_at(square)).collect()
+    }
+}
+
+impl TryFrom<Vec<Option<Piece>>> for Board {
+    type Error = BoardError;
+
+    fn try_from(squares: Vec<Option<Piece>>) -> Result<Self, Self::Error> {
+        if squares.len() != 64 {
+            return Err(BoardError::WrongLength(squares.len()));
+        }
+        let mut board = Board::default();
+        for (square, piece) in Square::all().zip(squares) {
+            board.set(square, piece);
+        }
+        Ok(board)
+    }
+}
@@ -0,0 +1,142 @@
+use crate::types::Color;
+
+// The clock only does arithmetic on milliseconds. Whoever runs it measures
+// how long the current turn has taken (the server uses monotonic time) and
+// passes that in as `elapsed_ms`.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum DelayMode {
+    /// The clock waits for the delay before it starts counting down.
+    Simple,
+    /// The clock counts down at once, and the time used up to the delay is
+    /// given back after the move.
+    Bronstein,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Delay {
+    pub mode: DelayMode,
+    pub ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TimeControl {
+    /// Starting time for each player.
+    pub base_ms: u64,
+    /// Fischer increment added after every move.
+    #[serde(default)]
+    pub increment_ms: u64,
+    #[serde(default)]
+    pub delay: Option<Delay>,
+}
+
+/// Correspondence play: each move must be made within `days_per_move` days
+/// of the previous one, with no clock running in between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Correspondence {
+    pub days_per_move: u32,
+}
+
+impl Correspondence {
+    pub fn time_per_move_ms(&self) -> u64 {
+        u64::from(self.days_per_move) * 86_400_000
+    }
+}
+
+/// Both players' remaining time. The remaining time of the running side is
+/// as of the start of its turn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Clock {
+    pub time_control: TimeControl,
+    pub white_ms: u64,
+    pub black_ms: u64,
+    /// The side whose time is running; `None` once the clock is stopped.
+    pub running: Option<Color>,
+}
+
+impl Clock {
+    /// A full clock with `turn`'s time running.
+    pub fn new(time_control: TimeControl, turn: Color) -> Self {
+        Self {
+            time_control,
+            white_ms: time_control.base_ms,
+            black_ms: time_control.base_ms,
+            running: Some(turn),
+        }
+    }
+
+    pub fn remaining(&self, color: Color) -> u64 {
+        match color {
+            Color::White => self.white_ms,
+            Color::Black => self.black_ms,
+        }
+    }
+
+    fn remaining_mut(&mut self, color: Color) -> &mut u64 {
+        match color {
+            Color::White => &mut self.white_ms,
+            Color::Black => &mut self.black_ms,
+        }
+    }
+
+    fn simple_delay_ms(&self) -> u64 {
+        match self.time_control.delay {
+            Some(Delay {
+                mode: DelayMode::Simple,
+                ms,
+            }) => ms,
+            _ => 0,
+        }
+    }
+
+    /// Time `color` has left after thinking for `elapsed_ms` on the current
+    /// move, before any increment or Bronstein refund.
+    pub fn time_left(&self, color: Color, elapsed_ms: u64) -> u64 {
+        let charged = elapsed_ms.saturating_sub(self.simple_delay_ms());
+        self.remaining(color).saturating_sub(charged)
+    }
+
+    /// Returns true if the running side has run out of time.
+    pub fn flagged(&self, elapsed_ms: u64) -> bool {
+        self.running
+            .is_some_and(|color| self.time_left(color, elapsed_ms) == 0)
+    }
+
+    /// Milliseconds until the running side's flag falls, if the clock runs.
+    pub fn time_to_flag(&self, elapsed_ms: u64) -> Option<u64> {
+        let color = self.running?;
+        Some(
+            self.remaining(color)
+                .saturating_add(self.simple_delay_ms())
+                .saturating_sub(elapsed_ms),
+        )
+    }
+
+    /// Ends the running side's turn after a move that took `elapsed_ms`,
+    /// crediting the increment and any Bronstein delay, and starts the
+    /// opponent's time.
+    pub fn press(&mut self, elapsed_ms: u64) {
+        let Some(color) = self.running else {
+            return;
+        };
+        let mut left = self.time_left(color, elapsed_ms);
+        if let Some(Delay {
+            mode: DelayMode::Bronstein,
+            ms,
+        }) = self.time_control.delay
+        {
+            left = left.saturating_add(elapsed_ms.min(ms));
+        }
+        *self.remaining_mut(color) = left.saturating_add(self.time_control.increment_ms);
+        self.running = Some(color.opposite());
+    }
+
+    /// Charges the running side for `elapsed_ms` and stops the clock.
+    pub fn stop(&mut self, elapsed_ms: u64) {
+        let Some(color) = self.running else {
+            return;
+        };
+        *self.remaining_mut(color) = self.time_left(color, elapsed_ms);
+        self.running = None;
+    }
+}
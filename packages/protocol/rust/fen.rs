@@ -0,0 +1,342 @@
+use crate::board::{Bitboard, Board};
+use crate::rules;
+use crate::types::*;
+use crate::zobrist::{self, ZobristKey};
+use std::fmt;
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Largest halfmove clock or fullmove number accepted, comfortably above the
+/// longest game the rules allow (under 9,000 moves).
+const MAX_MOVE_COUNTER: u32 = 10_000;
+
+// Castling rights in FEN order with the king and rook squares each one needs.
+const CASTLING_FIELDS: [(char, Color, &str, &str); 4] = [
+    ('K', Color::White, "e1", "h1"),
+    ('Q', Color::White, "e1", "a1"),
+    ('k', Color::Black, "e8", "h8"),
+    ('q', Color::Black, "e8", "a8"),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    WrongFieldCount(usize),
+    WrongRankCount(usize),
+    BadRankLength(u8),
+    InvalidPiece(char),
+    InvalidSideToMove(String),
+    InvalidCastling(String),
+    InvalidEnPassant(String),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+    KingCount { color: Color, count: usize },
+    PawnOnBackRank,
+    OpponentInCheck,
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::WrongFieldCount(count) => {
+                write!(f, "expected 4 or 6 space-separated fields, found {}", count)
+            }
+            FenError::WrongRankCount(count) => write!(f, "expected 8 ranks, found {}", count),
+            FenError::BadRankLength(rank) => {
+                write!(f, "rank {} does not describe exactly 8 squares", rank)
+            }
+            FenError::InvalidPiece(c) => write!(f, "'{}' is not a piece", c),
+            FenError::InvalidSideToMove(field) => {
+                write!(f, "side to move must be 'w' or 'b', found '{}'", field)
+            }
+            FenError::InvalidCastling(field) => {
+                write!(f, "castling rights '{}' do not match the position", field)
+            }
+            FenError::InvalidEnPassant(field) => {
+                write!(f, "'{}' is not a valid en passant square here", field)
+            }
+            FenError::InvalidHalfmoveClock(field) => {
+                write!(
+                    f,
+                    "halfmove clock '{}' is not a number up to {}",
+                    field, MAX_MOVE_COUNTER
+                )
+            }
+            FenError::InvalidFullmoveNumber(field) => {
+                write!(
+                    f,
+                    "fullmove number '{}' is not a number from 1 to {}",
+                    field, MAX_MOVE_COUNTER
+                )
+            }
+            FenError::KingCount { color, count } => {
+                write!(f, "expected one {:?} king, found {}", color, count)
+            }
+            FenError::PawnOnBackRank => write!(f, "pawns cannot stand on the first or last rank"),
+            FenError::OpponentInCheck => write!(f, "the side that just moved is in check"),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+fn piece_from_char(c: char) -> Option<Piece> {
+    let color = if c.is_ascii_uppercase() {
+        Color::White
+    } else {
+        Color::Black
+    };
+    let piece = match c.to_ascii_lowercase() {
+        'p' => PieceType::Pawn,
+        'n' => PieceType::Knight,
+        'b' => PieceType::Bishop,
+        'r' => PieceType::Rook,
+        'q' => PieceType::Queen,
+        'k' => PieceType::King,
+        _ => return None,
+    };
+    Some(Piece { color, piece })
+}
+
+fn piece_to_char(piece: Piece) -> char {
+    let c = match piece.piece {
+        PieceType::Pawn => 'p',
+        PieceType::Knight => 'n',
+        PieceType::Bishop => 'b',
+        PieceType::Rook => 'r',
+        PieceType::Queen => 'q',
+        PieceType::King => 'k',
+    };
+    match piece.color {
+        Color::White => c.to_ascii_uppercase(),
+        Color::Black => c,
+    }
+}
+
+// Rank 8 (row 0) and rank 1 (row 7).
+const BACK_RANKS: Bitboard = 0xff00_0000_0000_00ff;
+
+fn parse_board(field: &str) -> Result<Board, FenError> {
+    let ranks: Vec<&str> = field.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::WrongRankCount(ranks.len()));
+    }
+
+    let mut squares = Vec::with_capacity(64);
+    for (i, rank) in ranks.iter().enumerate() {
+        let start = squares.len();
+        for c in rank.chars() {
+            if let Some(empty) = c.to_digit(10).filter(|d| (1..=8).contains(d)) {
+                squares.extend(std::iter::repeat_n(None, empty as usize));
+            } else {
+                squares.push(Some(piece_from_char(c).ok_or(FenError::InvalidPiece(c))?));
+            }
+        }
+        if squares.len() - start != 8 {
+            return Err(FenError::BadRankLength(8 - i as u8));
+        }
+    }
+    let board = Board::try_from(squares).map_err(|_| FenError::WrongRankCount(ranks.len()))?;
+
+    for color in [Color::White, Color::Black] {
+        let count = board.pieces(color, PieceType::King).count_ones() as usize;
+        if count != 1 {
+            return Err(FenError::KingCount { color, count });
+        }
+    }
+    if board.by_type(PieceType::Pawn) & BACK_RANKS != 0 {
+        return Err(FenError::PawnOnBackRank);
+    }
+
+    Ok(board)
+}
+
+fn parse_castling(field: &str, board: &Board) -> Result<CastlingRights, FenError> {
+    let mut rights = CastlingRights::none();
+    if field == "-" {
+        return Ok(rights);
+    }
+
+    let invalid = || FenError::InvalidCastling(field.to_string());
+    for c in field.chars() {
+        let &(_, color, king, rook) = CASTLING_FIELDS
+            .iter()
+            .find(|(symbol, ..)| *symbol == c)
+            .ok_or_else(invalid)?;
+        let holds = |name: &str, piece: PieceType| {
+            name.parse::<Square>()
+                .ok()
+                .and_then(|sq| board.piece_at(sq))
+                == Some(Piece { color, piece })
+        };
+        if !holds(king, PieceType::King) || !holds(rook, PieceType::Rook) {
+            return Err(invalid());
+        }
+        let right = match c {
+            'K' => &mut rights.white_kingside,
+            'Q' => &mut rights.white_queenside,
+            'k' => &mut rights.black_kingside,
+            _ => &mut rights.black_queenside,
+        };
+        // Each letter may appear only once.
+        if *right {
+            return Err(invalid());
+        }
+        *right = true;
+    }
+    Ok(rights)
+}
+
+fn parse_en_passant(field: &str, board: &Board, turn: Color) -> Result<Option<Square>, FenError> {
+    if field == "-" {
+        return Ok(None);
+    }
+
+    let invalid = || FenError::InvalidEnPassant(field.to_string());
+    let square: Square = field.parse().map_err(|_| invalid())?;
+    // The target sits on the 6th rank for white to move (3rd for black), with
+    // the pawn that just double-stepped directly in front of it and the square
+    // it came from empty behind it.
+    let (target_rank, pawn_rank, origin_rank) = match turn {
+        Color::White => (5, 4, 6),
+        Color::Black => (2, 3, 1),
+    };
+    let pawn = Piece {
+        color: turn.opposite(),
+        piece: PieceType::Pawn,
+    };
+    let pawn_square = Square::from_file_rank(square.file(), pawn_rank).ok_or_else(invalid)?;
+    let origin = Square::from_file_rank(square.file(), origin_rank).ok_or_else(invalid)?;
+    if square.rank() != target_rank
+        || board.piece_at(square).is_some()
+        || board.piece_at(origin).is_some()
+        || board.piece_at(pawn_square) != Some(pawn)
+    {
+        return Err(invalid());
+    }
+    Ok(Some(square))
+}
+
+impl Position {
+    /// Parses a position in Forsyth-Edwards Notation. The halfmove clock and
+    /// fullmove number may be omitted, in which case they default to 0 and 1.
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() != 6 && fields.len() != 4 {
+            return Err(FenError::WrongFieldCount(fields.len()));
+        }
+
+        let board = parse_board(fields[0])?;
+        let turn = match fields[1] {
+            "w" => Color::White,
+            "b" => Color::Black,
+            other => return Err(FenError::InvalidSideToMove(other.to_string())),
+        };
+        let castling_rights = parse_castling(fields[2], &board)?;
+        let en_passant = parse_en_passant(fields[3], &board, turn)?;
+        let halfmove_clock = match fields.get(4) {
+            Some(field) => field
+                .parse()
+                .ok()
+                .filter(|&n| n <= MAX_MOVE_COUNTER)
+                .ok_or_else(|| FenError::InvalidHalfmoveClock(field.to_string()))?,
+            None => 0,
+        };
+        let fullmove_number = match fields.get(5) {
+            Some(field) => field
+                .parse()
+                .ok()
+                .filter(|&n| (1..=MAX_MOVE_COUNTER).contains(&n))
+                .ok_or_else(|| FenError::InvalidFullmoveNumber(field.to_string()))?,
+            None => 1,
+        };
+
+        if rules::is_in_check(&board, turn.opposite()) {
+            return Err(FenError::OpponentInCheck);
+        }
+
+        let mut position = Position {
+            board,
+            turn,
+            castling_rights,
+            en_passant,
+            halfmove_clock,
+            fullmove_number,
+            zobrist: ZobristKey::default(),
+        };
+        position.zobrist = zobrist::hash(&position);
+        Ok(position)
+    }
+
+    /// Serializes the position in Forsyth-Edwards Notation.
+    pub fn to_fen(&self) -> String {
+        let mut placement = String::new();
+        let mut empty = 0;
+        for square in Square::all() {
+            match self.board.piece_at(square) {
+                Some(piece) => {
+                    if empty > 0 {
+                        placement.push_str(&empty.to_string());
+                        empty = 0;
+                    }
+                    placement.push(piece_to_char(piece));
+                }
+                None => empty += 1,
+            }
+            if square.file() == 7 {
+                if empty > 0 {
+                    placement.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                if square.rank() > 0 {
+                    placement.push('/');
+                }
+            }
+        }
+
+        let turn = match self.turn {
+            Color::White => "w",
+            Color::Black => "b",
+        };
+
+        let rights = &self.castling_rights;
+        let flags = [
+            rights.white_kingside,
+            rights.white_queenside,
+            rights.black_kingside,
+            rights.black_queenside,
+        ];
+        let castling: String = CASTLING_FIELDS
+            .iter()
+            .zip(flags)
+            .filter(|(_, allowed)| *allowed)
+            .map(|((symbol, ..), _)| *symbol)
+            .collect();
+        let castling = if castling.is_empty() {
+            "-".to_string()
+        } else {
+            castling
+        };
+
+        let en_passant = self
+            .en_passant
+            .map(|square| square.to_string())
+            .unwrap_or_else(|| "-".to_string());
+
+        format!(
+            "{} {} {} {} {} {}",
+            placement, turn, castling, en_passant, self.halfmove_clock, self.fullmove_number
+        )
+    }
+}
+
+impl GameState {
+    /// Starts a game from a FEN position. See [`Position::from_fen`].
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        Position::from_fen(fen).map(GameState::from_position)
+    }
+
+    /// Serializes the current position in Forsyth-Edwards Notation.
+    pub fn to_fen(&self) -> String {
+        self.position.to_fen()
+    }
+}
@@ -1 +1,12 @@
+pub mod board;
+pub mod clock;
+pub mod fen;
+pub mod perft;
+pub mod pgn;
+pub mod rules;
+pub mod san;
 pub mod types;
+pub mod uci;
+pub mod zobrist;
+
+pub const PROTOCOL_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -0,0 +1,36 @@
+use crate::types::*;
+
+/// Counts the leaf nodes of the legal move tree `depth` plies deep. Comparing
+/// against published counts is the standard check of a move generator.
+pub fn perft(position: &Position, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = position.legal_moves();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+    moves
+        .iter()
+        .map(|move_| {
+            let mut child = *position;
+            child.play(move_);
+            perft(&child, depth - 1)
+        })
+        .sum()
+}
+
+/// Splits [`perft`] by root move, which narrows a wrong total down to the
+/// move whose subtree is off.
+pub fn divide(position: &Position, depth: u32) -> Vec<(Move, u64)> {
+    position
+        .legal_moves()
+        .into_iter()
+        .map(|move_| {
+            let mut child = *position;
+            child.play(&move_);
+            let nodes = perft(&child, depth.saturating_sub(1));
+            (move_, nodes)
+        })
+        .collect()
+}
@@ -0,0 +1,317 @@
+use crate::fen::{FenError, STARTING_FEN};
+use crate::rules;
+use crate::san::{parse_san, to_san, SanError};
+use crate::types::*;
+use std::fmt;
+
+const MAX_LINE_LENGTH: usize = 80;
+
+/// Values for the Seven Tag Roster. `Result` is taken from the game itself.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnTags {
+    pub event: String,
+    pub site: String,
+    pub date: String,
+    pub round: String,
+    pub white: String,
+    pub black: String,
+}
+
+impl Default for PgnTags {
+    fn default() -> Self {
+        Self {
+            event: "?".to_string(),
+            site: "?".to_string(),
+            date: "????.??.??".to_string(),
+            round: "?".to_string(),
+            white: "?".to_string(),
+            black: "?".to_string(),
+        }
+    }
+}
+
+/// The PGN result token for `result`, `*` while the game is in progress.
+pub fn result_token(result: Option<GameResult>) -> &'static str {
+    match result {
+        Some(GameResult::WhiteWins) => "1-0",
+        Some(GameResult::BlackWins) => "0-1",
+        Some(GameResult::Draw) => "1/2-1/2",
+        None => "*",
+    }
+}
+
+/// A human readable description of how the game ended.
+pub fn describe_result(result: GameResult, termination: Termination) -> String {
+    let winner = match result {
+        GameResult::WhiteWins => Some(Color::White),
+        GameResult::BlackWins => Some(Color::Black),
+        GameResult::Draw => None,
+    };
+    let reason = match termination {
+        Termination::Checkmate => "checkmate",
+        Termination::Stalemate => "stalemate",
+        Termination::InsufficientMaterial => "insufficient material",
+        Termination::FiftyMoveRule => "the fifty-move rule",
+        Termination::SeventyFiveMoveRule => "the seventy-five-move rule",
+        Termination::ThreefoldRepetition => "threefold repetition",
+        Termination::FivefoldRepetition => "fivefold repetition",
+        Termination::Resignation => "resignation",
+        Termination::Agreement => "agreement",
+        Termination::Timeout => "timeout",
+        Termination::TimeoutVsInsufficientMaterial => "timeout vs insufficient material",
+        Termination::Abandonment => "abandonment",
+    };
+    match winner {
+        Some(color) => format!("{:?} wins by {}", color, reason),
+        None => format!("Draw by {}", reason),
+    }
+}
+
+fn termination_tag(state: &GameState) -> &'static str {
+    match state.termination {
+        Some(Termination::Timeout | Termination::TimeoutVsInsufficientMaterial) => "time forfeit",
+        Some(Termination::Abandonment) => "abandoned",
+        Some(_) => "normal",
+        None => "unterminated",
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Appends `tokens` to `out`, breaking lines before they exceed 80 characters.
+fn push_wrapped(out: &mut String, tokens: &[String]) {
+    let mut line_length = 0;
+    for token in tokens {
+        if line_length > 0 && line_length + 1 + token.len() > MAX_LINE_LENGTH {
+            out.push('\n');
+            line_length = 0;
+        } else if line_length > 0 {
+            out.push(' ');
+            line_length += 1;
+        }
+        out.push_str(token);
+        line_length += token.len();
+    }
+    out.push('\n');
+}
+
+/// Renders the game that started at `initial` and reached `state` as PGN.
+/// The moves are replayed from `initial`, so `state.move_history` must have
+/// been played from that position.
+pub fn to_pgn(initial: &GameState, state: &GameState, tags: &PgnTags) -> String {
+    let result = result_token(state.result);
+    let mut out = String::new();
+    for (name, value) in [
+        ("Event", tags.event.as_str()),
+        ("Site", tags.site.as_str()),
+        ("Date", tags.date.as_str()),
+        ("Round", tags.round.as_str()),
+        ("White", tags.white.as_str()),
+        ("Black", tags.black.as_str()),
+        ("Result", result),
+    ] {
+        out.push_str(&format!("[{} \"{}\"]\n", name, escape(value)));
+    }
+    let initial_fen = initial.to_fen();
+    if initial_fen != STARTING_FEN {
+        out.push_str("[SetUp \"1\"]\n");
+        out.push_str(&format!("[FEN \"{}\"]\n", initial_fen));
+    }
+    out.push_str(&format!("[Termination \"{}\"]\n\n", termination_tag(state)));
+
+    let mut tokens = Vec::new();
+    let mut replay = initial.clone();
+    for (i, move_) in state.move_history.iter().enumerate() {
+        let position = &replay.position;
+        if position.turn == Color::White {
+            tokens.push(format!("{}.", position.fullmove_number));
+        } else if i == 0 {
+            tokens.push(format!("{}...", position.fullmove_number));
+        }
+        tokens.push(to_san(&replay, move_));
+        if rules::apply_move(&mut replay, move_).is_err() {
+            break;
+        }
+    }
+    if let (Some(game_result), Some(termination)) = (state.result, state.termination) {
+        tokens.push(format!("{{{}}}", describe_result(game_result, termination)));
+    }
+    tokens.push(result.to_string());
+
+    push_wrapped(&mut out, &tokens);
+    out
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnError {
+    UnterminatedTag,
+    UnterminatedComment,
+    UnbalancedVariation,
+    InvalidFen(FenError),
+    IllegalMove {
+        ply: usize,
+        token: String,
+        error: SanError,
+    },
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PgnError::UnterminatedTag => write!(f, "tag pair is missing its closing bracket"),
+            PgnError::UnterminatedComment => write!(f, "comment is missing its closing brace"),
+            PgnError::UnbalancedVariation => write!(f, "variation parentheses do not match"),
+            PgnError::InvalidFen(e) => write!(f, "invalid FEN tag: {}", e),
+            PgnError::IllegalMove { ply, token, error } => {
+                write!(f, "ply {} ('{}'): {}", ply, token, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+/// A game read from PGN. Comments, NAGs and variations are dropped, leaving
+/// the tag pairs in file order and the SAN tokens of the main line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub moves: Vec<String>,
+    pub result: String,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The position the game starts from, taken from the `FEN` tag if present.
+    pub fn initial_state(&self) -> Result<GameState, PgnError> {
+        GameState::from_fen(self.tag("FEN").unwrap_or(STARTING_FEN)).map_err(PgnError::InvalidFen)
+    }
+
+    /// Plays the main line through the rules engine, returning the moves and
+    /// the position they lead to. Plies are counted from 1.
+    pub fn replay(&self) -> Result<(Vec<Move>, GameState), PgnError> {
+        let mut state = self.initial_state()?;
+        let mut moves = Vec::with_capacity(self.moves.len());
+        for (i, token) in self.moves.iter().enumerate() {
+            let illegal = |error| PgnError::IllegalMove {
+                ply: i + 1,
+                token: token.clone(),
+                error,
+            };
+            let move_ = parse_san(&state, token).map_err(illegal)?;
+            // A result reached by the rules ends the game; moves after it are
+            // as illegal as any other.
+            rules::apply_move(&mut state, &move_)
+                .map_err(|_| illegal(SanError::Illegal(token.clone())))?;
+            moves.push(move_);
+        }
+        Ok((moves, state))
+    }
+}
+
+fn is_result(token: &str) -> bool {
+    matches!(token, "1-0" | "0-1" | "1/2-1/2" | "*")
+}
+
+fn parse_tag(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+) -> Result<(String, String), PgnError> {
+    let mut name = String::new();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() || c == '"' || c == ']' {
+            break;
+        }
+        name.push(c);
+        chars.next();
+    }
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    if chars.next() != Some('"') {
+        return Err(PgnError::UnterminatedTag);
+    }
+    let mut value = String::new();
+    loop {
+        match chars.next() {
+            Some('\\') => value.extend(chars.next()),
+            Some('"') => break,
+            Some(c) => value.push(c),
+            None => return Err(PgnError::UnterminatedTag),
+        }
+    }
+    while chars.next_if(|c| c.is_whitespace()).is_some() {}
+    if chars.next() != Some(']') {
+        return Err(PgnError::UnterminatedTag);
+    }
+    Ok((name, value))
+}
+
+/// Reads the first game in `text`. Move numbers and annotation glyphs such as
+/// `!?` are accepted but not kept; the result token defaults to `*` if the
+/// movetext ends without one.
+pub fn parse_pgn(text: &str) -> Result<PgnGame, PgnError> {
+    let mut game = PgnGame {
+        tags: vec![],
+        moves: vec![],
+        result: "*".to_string(),
+    };
+    let mut chars = text.chars().peekable();
+    let mut depth = 0usize;
+    let mut at_line_start = true;
+
+    while let Some(c) = chars.next() {
+        let line_start = at_line_start;
+        at_line_start = c == '\n';
+        match c {
+            c if c.is_whitespace() => {}
+            // Escaped lines and `;` comments run to the end of the line.
+            '%' if line_start => while chars.next_if(|&c| c != '\n').is_some() {},
+            ';' => while chars.next_if(|&c| c != '\n').is_some() {},
+            '{' => {
+                if !chars.by_ref().any(|c| c == '}') {
+                    return Err(PgnError::UnterminatedComment);
+                }
+            }
+            '(' => depth += 1,
+            ')' => depth = depth.checked_sub(1).ok_or(PgnError::UnbalancedVariation)?,
+            // A tag after the movetext belongs to the next game.
+            '[' if depth == 0 && !game.moves.is_empty() => break,
+            '[' if depth == 0 => game.tags.push(parse_tag(&mut chars)?),
+            _ => {
+                let mut token = c.to_string();
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace() && !"{}();[]".contains(*c))
+                {
+                    token.push(c);
+                }
+                if depth > 0 || token.starts_with('$') {
+                    continue;
+                }
+                if is_result(&token) {
+                    game.result = token;
+                    break;
+                }
+                // Move numbers may be glued to the move, as in `12.e4`.
+                let unnumbered = token.trim_start_matches(|c: char| c.is_ascii_digit());
+                let san = match unnumbered.strip_prefix('.') {
+                    Some(rest) => rest.trim_start_matches('.'),
+                    None => &token,
+                }
+                .trim_end_matches(['!', '?']);
+                if !san.is_empty() {
+                    game.moves.push(san.to_string());
+                }
+            }
+        }
+    }
+
+    if depth > 0 {
+        return Err(PgnError::UnbalancedVariation);
+    }
+    Ok(game)
+}
@@ -0,0 +1,722 @@
+use crate::board::{self, square_bit, squares, Bitboard, Board, LIGHT_SQUARES};
+use crate::types::*;
+use crate::zobrist::{self, ZobristKey};
+use std::fmt;
+
+// Squares are indexed as `row * 8 + col`, where row 0 is black's back rank
+// (rank 8) and row 7 is white's back rank (rank 1), matching the board built
+// by the server.
+
+const fn square_from_index(index: u8) -> Square {
+    match Square::new(index) {
+        Some(square) => square,
+        None => panic!("square index off the board"),
+    }
+}
+
+// Corner squares whose rook (or king, on the e-file) losing its first move
+// forfeits the matching castling right.
+const CASTLING_SQUARES: [(Square, Color, bool, bool); 6] = [
+    (square_from_index(60), Color::White, true, true),
+    (square_from_index(63), Color::White, true, false),
+    (square_from_index(56), Color::White, false, true),
+    (square_from_index(4), Color::Black, true, true),
+    (square_from_index(7), Color::Black, true, false),
+    (square_from_index(0), Color::Black, false, true),
+];
+
+const PROMOTIONS: [Option<PieceType>; 4] = [
+    Some(PieceType::Queen),
+    Some(PieceType::Rook),
+    Some(PieceType::Bishop),
+    Some(PieceType::Knight),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MoveError {
+    GameOver,
+    SameSquare,
+    NoPiece(Square),
+    NotYourPiece,
+    CaptureOwnPiece,
+    InvalidPattern(PieceType),
+    PathBlocked,
+    KingInCheck,
+    CastlingUnavailable,
+    CastleThroughCheck,
+    PromotionRequired,
+    InvalidPromotion(PieceType),
+    UnexpectedPromotion,
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MoveError::GameOver => write!(f, "The game is over"),
+            MoveError::SameSquare => write!(f, "A move must change squares"),
+            MoveError::NoPiece(square) => write!(f, "There is no piece on square {}", square),
+            MoveError::NotYourPiece => write!(f, "That piece belongs to your opponent"),
+            MoveError::CaptureOwnPiece => write!(f, "You cannot capture your own piece"),
+            MoveError::InvalidPattern(piece) => write!(f, "A {:?} cannot move like that", piece),
+            MoveError::PathBlocked => write!(f, "The path is blocked"),
+            MoveError::KingInCheck => write!(f, "That move would leave your king in check"),
+            MoveError::CastlingUnavailable => write!(f, "Castling is not available on that side"),
+            MoveError::CastleThroughCheck => {
+                write!(f, "You cannot castle out of or through check")
+            }
+            MoveError::PromotionRequired => {
+                write!(f, "A pawn reaching the last rank must be promoted")
+            }
+            MoveError::InvalidPromotion(piece) => {
+                write!(f, "A pawn cannot promote to a {:?}", piece)
+            }
+            MoveError::UnexpectedPromotion => {
+                write!(f, "Only a pawn reaching the last rank can promote")
+            }
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+fn row(square: Square) -> i8 {
+    (square.index() / 8) as i8
+}
+
+fn col(square: Square) -> i8 {
+    square.file() as i8
+}
+
+fn square_at(r: i8, c: i8) -> Square {
+    square_from_index((r * 8 + c) as u8)
+}
+
+fn offset(square: Square, d_row: i8, d_col: i8) -> Option<Square> {
+    let r = row(square) + d_row;
+    let c = col(square) + d_col;
+    if (0..8).contains(&r) && (0..8).contains(&c) {
+        Some(square_at(r, c))
+    } else {
+        None
+    }
+}
+
+/// Direction a pawn of the given color advances in, as a row delta.
+fn pawn_direction(color: Color) -> i8 {
+    match color {
+        Color::White => -1,
+        Color::Black => 1,
+    }
+}
+
+fn pawn_start_row(color: Color) -> i8 {
+    match color {
+        Color::White => 6,
+        Color::Black => 1,
+    }
+}
+
+fn back_row(color: Color) -> i8 {
+    match color {
+        Color::White => 7,
+        Color::Black => 0,
+    }
+}
+
+/// The pieces of color `by` that attack `square`.
+pub fn attackers(board: &Board, square: Square, by: Color) -> Bitboard {
+    let occupied = board.occupied();
+    let pieces = |piece: PieceType| board.pieces(by, piece);
+    let queens = pieces(PieceType::Queen);
+
+    // A pawn of `by` attacks the square exactly when a pawn of the other
+    // color on the square would attack the pawn.
+    (board::pawn_attacks(by.opposite(), square) & pieces(PieceType::Pawn))
+        | (board::knight_attacks(square) & pieces(PieceType::Knight))
+        | (board::king_attacks(square) & pieces(PieceType::King))
+        | (board::bishop_attacks(square, occupied) & (pieces(PieceType::Bishop) | queens))
+        | (board::rook_attacks(square, occupied) & (pieces(PieceType::Rook) | queens))
+}
+
+/// Returns true if any piece of color `by` attacks `square`.
+pub fn is_square_attacked(board: &Board, square: Square, by: Color) -> bool {
+    attackers(board, square, by) != 0
+}
+
+/// Returns true if the king of `color` is currently attacked.
+pub fn is_in_check(board: &Board, color: Color) -> bool {
+    board
+        .king_square(color)
+        .is_some_and(|sq| is_square_attacked(board, sq, color.opposite()))
+}
+
+fn check_slide(board: &Board, from: Square, to: Square) -> Result<(), MoveError> {
+    let d_row = (row(to) - row(from)).signum();
+    let d_col = (col(to) - col(from)).signum();
+    let mut current = offset(from, d_row, d_col);
+    while let Some(sq) = current {
+        if sq == to {
+            return Ok(());
+        }
+        if board.piece_at(sq).is_some() {
+            return Err(MoveError::PathBlocked);
+        }
+        current = offset(sq, d_row, d_col);
+    }
+    Ok(())
+}
+
+fn check_castle(
+    position: &Position,
+    color: Color,
+    from: Square,
+    to: Square,
+) -> Result<(), MoveError> {
+    let board = &position.board;
+    let r = back_row(color);
+    if from != square_at(r, 4) || row(to) != r {
+        return Err(MoveError::InvalidPattern(PieceType::King));
+    }
+
+    let kingside = col(to) == 6;
+    let allowed = if kingside {
+        position.castling_rights.kingside(color)
+    } else {
+        position.castling_rights.queenside(color)
+    };
+    let rook_square = square_at(r, if kingside { 7 } else { 0 });
+    let rook = Piece {
+        color,
+        piece: PieceType::Rook,
+    };
+    if !allowed || board.piece_at(rook_square) != Some(rook) {
+        return Err(MoveError::CastlingUnavailable);
+    }
+
+    check_slide(board, from, rook_square)?;
+
+    let passed = square_at(r, if kingside { 5 } else { 3 });
+    if is_in_check(board, color) || is_square_attacked(board, passed, color.opposite()) {
+        return Err(MoveError::CastleThroughCheck);
+    }
+
+    Ok(())
+}
+
+/// Checks that `piece` can move from `from` to `to` according to its movement
+/// pattern, ignoring king safety.
+fn check_pattern(
+    position: &Position,
+    piece: Piece,
+    from: Square,
+    to: Square,
+) -> Result<(), MoveError> {
+    let board = &position.board;
+    let d_row = row(to) - row(from);
+    let d_col = col(to) - col(from);
+    let invalid = Err(MoveError::InvalidPattern(piece.piece));
+
+    match piece.piece {
+        PieceType::Pawn => {
+            let dir = pawn_direction(piece.color);
+            let target = board.piece_at(to);
+            if d_col == 0 && d_row == dir {
+                if target.is_some() {
+                    return Err(MoveError::PathBlocked);
+                }
+                Ok(())
+            } else if d_col == 0 && d_row == 2 * dir && row(from) == pawn_start_row(piece.color) {
+                let middle = offset(from, dir, 0).ok_or(MoveError::PathBlocked)?;
+                if board.piece_at(middle).is_some() || target.is_some() {
+                    return Err(MoveError::PathBlocked);
+                }
+                Ok(())
+            } else if d_col.abs() == 1
+                && d_row == dir
+                && (target.is_some() || position.en_passant == Some(to))
+            {
+                Ok(())
+            } else {
+                invalid
+            }
+        }
+        PieceType::Knight => {
+            if (d_row.abs(), d_col.abs()) == (1, 2) || (d_row.abs(), d_col.abs()) == (2, 1) {
+                Ok(())
+            } else {
+                invalid
+            }
+        }
+        PieceType::King => {
+            if d_row.abs() <= 1 && d_col.abs() <= 1 {
+                Ok(())
+            } else if d_row == 0 && d_col.abs() == 2 {
+                check_castle(position, piece.color, from, to)
+            } else {
+                invalid
+            }
+        }
+        PieceType::Rook => {
+            if d_row == 0 || d_col == 0 {
+                check_slide(board, from, to)
+            } else {
+                invalid
+            }
+        }
+        PieceType::Bishop => {
+            if d_row.abs() == d_col.abs() {
+                check_slide(board, from, to)
+            } else {
+                invalid
+            }
+        }
+        PieceType::Queen => {
+            if d_row == 0 || d_col == 0 || d_row.abs() == d_col.abs() {
+                check_slide(board, from, to)
+            } else {
+                invalid
+            }
+        }
+    }
+}
+
+fn check_promotion(piece: Piece, move_: &Move) -> Result<(), MoveError> {
+    let promotes =
+        piece.piece == PieceType::Pawn && row(move_.to) == back_row(piece.color.opposite());
+    match (promotes, move_.promotion) {
+        (true, None) => Err(MoveError::PromotionRequired),
+        (true, Some(p @ (PieceType::King | PieceType::Pawn))) => {
+            Err(MoveError::InvalidPromotion(p))
+        }
+        (false, Some(_)) => Err(MoveError::UnexpectedPromotion),
+        _ => Ok(()),
+    }
+}
+
+/// Rook origin and destination for a castling king move, if `move_` is one.
+fn castling_rook(piece: Piece, move_: &Move) -> Option<(Square, Square)> {
+    let (from, to) = (move_.from, move_.to);
+    if piece.piece != PieceType::King || (col(to) - col(from)).abs() != 2 {
+        return None;
+    }
+    Some(if col(to) > col(from) {
+        (square_at(row(from), 7), square_at(row(from), 5))
+    } else {
+        (square_at(row(from), 0), square_at(row(from), 3))
+    })
+}
+
+/// Moves a piece on `board`, relocating the rook when castling, removing the
+/// captured pawn on an en passant capture and swapping in the promoted piece.
+/// Returns the captured piece.
+fn move_piece(board: &mut Board, move_: &Move, en_passant: Option<Square>) -> Option<Piece> {
+    let (from, to) = (move_.from, move_.to);
+    let piece = board.take(from)?;
+    let mut captured = board.take(to);
+
+    if piece.piece == PieceType::Pawn
+        && captured.is_none()
+        && en_passant == Some(to)
+        && col(from) != col(to)
+    {
+        captured = board.take(square_at(row(from), col(to)));
+    }
+    if let Some((rook_from, rook_to)) = castling_rook(piece, move_) {
+        let rook = board.take(rook_from);
+        board.set(rook_to, rook);
+    }
+
+    let placed = match move_.promotion {
+        Some(promotion) if piece.piece == PieceType::Pawn => Piece {
+            color: piece.color,
+            piece: promotion,
+        },
+        _ => piece,
+    };
+    board.set(to, Some(placed));
+    captured
+}
+
+fn update_castling_rights(rights: &mut CastlingRights, square: Square) {
+    for (corner, color, kingside, queenside) in CASTLING_SQUARES {
+        if corner != square {
+            continue;
+        }
+        match color {
+            Color::White => {
+                rights.white_kingside &= !kingside;
+                rights.white_queenside &= !queenside;
+            }
+            Color::Black => {
+                rights.black_kingside &= !kingside;
+                rights.black_queenside &= !queenside;
+            }
+        }
+    }
+}
+
+impl Position {
+    /// Checks whether `move_` is legal for the side to move.
+    pub fn validate_move(&self, move_: &Move) -> Result<(), MoveError> {
+        if move_.from == move_.to {
+            return Err(MoveError::SameSquare);
+        }
+
+        let board = &self.board;
+        let piece = board
+            .piece_at(move_.from)
+            .ok_or(MoveError::NoPiece(move_.from))?;
+        if piece.color != self.turn {
+            return Err(MoveError::NotYourPiece);
+        }
+        if board
+            .piece_at(move_.to)
+            .is_some_and(|p| p.color == piece.color)
+        {
+            return Err(MoveError::CaptureOwnPiece);
+        }
+
+        check_pattern(self, piece, move_.from, move_.to)?;
+        check_promotion(piece, move_)?;
+
+        let mut after = *self;
+        after.play(move_);
+        if is_in_check(&after.board, piece.color) {
+            return Err(MoveError::KingInCheck);
+        }
+
+        Ok(())
+    }
+
+    /// Plays `move_` without checking that it is legal and returns the
+    /// captured piece, if any.
+    pub fn play(&mut self, move_: &Move) -> Option<Piece> {
+        let piece = self.board.piece_at(move_.from)?;
+        let mut key = self.zobrist.0
+            ^ zobrist::side_key(self.turn)
+            ^ zobrist::castling_key(self.castling_rights)
+            ^ zobrist::en_passant_key(self);
+
+        let before = self.board;
+        let captured = move_piece(&mut self.board, move_, self.en_passant);
+        // Every square the move can change: origin, target, an en passant
+        // victim beside the origin and the castling rook's two squares.
+        let mut touched = square_bit(move_.from)
+            | square_bit(move_.to)
+            | square_bit(square_at(row(move_.from), col(move_.to)));
+        if let Some((rook_from, rook_to)) = castling_rook(piece, move_) {
+            touched |= square_bit(rook_from) | square_bit(rook_to);
+        }
+        for square in squares(touched) {
+            key ^= zobrist::piece_key(before.piece_at(square), square)
+                ^ zobrist::piece_key(self.board.piece_at(square), square);
+        }
+
+        update_castling_rights(&mut self.castling_rights, move_.from);
+        update_castling_rights(&mut self.castling_rights, move_.to);
+
+        self.en_passant =
+            if piece.piece == PieceType::Pawn && (row(move_.to) - row(move_.from)).abs() == 2 {
+                Some(square_at(
+                    (row(move_.from) + row(move_.to)) / 2,
+                    col(move_.from),
+                ))
+            } else {
+                None
+            };
+        self.halfmove_clock = if piece.piece == PieceType::Pawn || captured.is_some() {
+            0
+        } else {
+            self.halfmove_clock.saturating_add(1)
+        };
+        if self.turn == Color::Black {
+            self.fullmove_number = self.fullmove_number.saturating_add(1);
+        }
+        self.turn = self.turn.opposite();
+
+        self.zobrist = ZobristKey(
+            key ^ zobrist::side_key(self.turn)
+                ^ zobrist::castling_key(self.castling_rights)
+                ^ zobrist::en_passant_key(self),
+        );
+        captured
+    }
+
+    /// Returns true if the side to move has a legal en passant capture.
+    pub fn can_capture_en_passant(&self) -> bool {
+        let Some(target) = self.en_passant else {
+            return false;
+        };
+        let capturers = board::pawn_attacks(self.turn.opposite(), target)
+            & self.board.pieces(self.turn, PieceType::Pawn);
+        squares(capturers).any(|from| {
+            let capture = Move {
+                from,
+                to: target,
+                promotion: None,
+            };
+            let mut after = self.board;
+            move_piece(&mut after, &capture, self.en_passant);
+            !is_in_check(&after, self.turn)
+        })
+    }
+
+    /// Returns true if the side to move is in check.
+    pub fn is_in_check(&self) -> bool {
+        is_in_check(&self.board, self.turn)
+    }
+
+    /// Squares of the pieces giving check to the side to move.
+    pub fn checkers(&self) -> Vec<Square> {
+        match self.board.king_square(self.turn) {
+            Some(king) => squares(attackers(&self.board, king, self.turn.opposite())).collect(),
+            None => vec![],
+        }
+    }
+
+    /// Lists every legal move for the side to move.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let color = self.turn;
+        let board = &self.board;
+        let own = board.by_color(color);
+        let occupied = board.occupied();
+
+        let mut moves = Vec::new();
+        for from in squares(own) {
+            let Some(piece) = board.piece_at(from) else {
+                continue;
+            };
+            let targets = match piece.piece {
+                PieceType::Pawn => self.pawn_targets(from),
+                PieceType::Knight => board::knight_attacks(from),
+                PieceType::Bishop => board::bishop_attacks(from, occupied),
+                PieceType::Rook => board::rook_attacks(from, occupied),
+                PieceType::Queen => board::queen_attacks(from, occupied),
+                PieceType::King => board::king_attacks(from) | self.castling_targets(from),
+            } & !own;
+
+            for to in squares(targets) {
+                let promotes =
+                    piece.piece == PieceType::Pawn && row(to) == back_row(color.opposite());
+                let candidates: &[Option<PieceType>] = if promotes { &PROMOTIONS } else { &[None] };
+                for &promotion in candidates {
+                    let move_ = Move {
+                        from,
+                        to,
+                        promotion,
+                    };
+                    let mut after = *self;
+                    after.play(&move_);
+                    if !is_in_check(&after.board, color) {
+                        moves.push(move_);
+                    }
+                }
+            }
+        }
+        moves
+    }
+
+    /// Pushes and captures, including en passant, for the pawn on `from`.
+    fn pawn_targets(&self, from: Square) -> Bitboard {
+        let color = self.turn;
+        let empty = |square: &Square| self.board.piece_at(*square).is_none();
+        let dir = pawn_direction(color);
+
+        let mut targets = 0;
+        if let Some(one) = offset(from, dir, 0).filter(empty) {
+            targets |= square_bit(one);
+            if row(from) == pawn_start_row(color) {
+                if let Some(two) = offset(one, dir, 0).filter(empty) {
+                    targets |= square_bit(two);
+                }
+            }
+        }
+        let capturable =
+            self.board.by_color(color.opposite()) | self.en_passant.map_or(0, square_bit);
+        targets | (board::pawn_attacks(color, from) & capturable)
+    }
+
+    fn castling_targets(&self, from: Square) -> Bitboard {
+        let r = back_row(self.turn);
+        [square_at(r, 6), square_at(r, 2)]
+            .into_iter()
+            .filter(|&to| check_castle(self, self.turn, from, to).is_ok())
+            .fold(0, |targets, to| targets | square_bit(to))
+    }
+}
+
+/// Checks whether `move_` is legal for the side to move in `state`.
+pub fn validate_move(state: &GameState, move_: &Move) -> Result<(), MoveError> {
+    if state.result.is_some() {
+        return Err(MoveError::GameOver);
+    }
+    state.position.validate_move(move_)
+}
+
+/// Everything [`undo_move`] needs to take back a move played with
+/// [`apply_move`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Undo {
+    pub move_: Move,
+    /// The position before the move.
+    pub position: Position,
+    pub captured: Option<Piece>,
+}
+
+/// Validates `move_` and, if it is legal, plays it on `state`.
+pub fn apply_move(state: &mut GameState, move_: &Move) -> Result<Undo, MoveError> {
+    validate_move(state, move_)?;
+
+    let position = state.position;
+    let captured = state.position.play(move_);
+    if let Some(taken) = captured {
+        state.taken_pieces.push(position.turn, taken);
+    }
+    state.material_balance = material_balance(&state.position.board);
+    state.move_history.push(move_.clone());
+    state.position_history.push(state.position.zobrist);
+
+    if let Some((result, termination)) = outcome(state) {
+        state.result = Some(result);
+        state.termination = Some(termination);
+    }
+
+    Ok(Undo {
+        move_: move_.clone(),
+        position,
+        captured,
+    })
+}
+
+/// Takes back the last move played on `state`, which must be the one `undo`
+/// was returned for.
+pub fn undo_move(state: &mut GameState, undo: &Undo) {
+    state.position = undo.position;
+    if undo.captured.is_some() {
+        state.taken_pieces.pop(undo.position.turn);
+    }
+    state.material_balance = material_balance(&state.position.board);
+    state.move_history.pop();
+    state.position_history.pop();
+    state.result = None;
+    state.termination = None;
+}
+
+/// Lists every legal move for the side to move, none once the game is over.
+pub fn legal_moves(state: &GameState) -> Vec<Move> {
+    if state.result.is_some() {
+        return vec![];
+    }
+    state.position.legal_moves()
+}
+
+/// Returns the result of the game if it has ended by rule: checkmate,
+/// stalemate, insufficient material, the 75-move rule or fivefold repetition.
+pub fn outcome(state: &GameState) -> Option<(GameResult, Termination)> {
+    let position = &state.position;
+    if position.legal_moves().is_empty() {
+        return if position.is_in_check() {
+            Some((
+                GameResult::win_for(position.turn.opposite()),
+                Termination::Checkmate,
+            ))
+        } else {
+            Some((GameResult::Draw, Termination::Stalemate))
+        };
+    }
+
+    let termination = if is_insufficient_material(&position.board) {
+        Termination::InsufficientMaterial
+    } else if position.halfmove_clock >= 150 {
+        Termination::SeventyFiveMoveRule
+    } else if repetition_count(state) >= 5 {
+        Termination::FivefoldRepetition
+    } else {
+        return None;
+    };
+    Some((GameResult::Draw, termination))
+}
+
+/// Returns the draw that could be claimed in the current position, if any.
+pub fn claimable_draw(state: &GameState) -> Option<Termination> {
+    if state.position.halfmove_clock >= 100 {
+        Some(Termination::FiftyMoveRule)
+    } else if repetition_count(state) >= 3 {
+        Some(Termination::ThreefoldRepetition)
+    } else {
+        None
+    }
+}
+
+/// White's material on `board` minus black's, in pawns.
+pub fn material_balance(board: &Board) -> i32 {
+    board
+        .iter()
+        .map(|(_, p)| match p.color {
+            Color::White => p.piece.value(),
+            Color::Black => -p.piece.value(),
+        })
+        .sum()
+}
+
+/// Returns true if neither side has enough material left to deliver mate.
+pub fn is_insufficient_material(board: &Board) -> bool {
+    let heavy = board.by_type(PieceType::Pawn)
+        | board.by_type(PieceType::Rook)
+        | board.by_type(PieceType::Queen);
+    if heavy != 0 {
+        return false;
+    }
+
+    let knights = board.by_type(PieceType::Knight);
+    let bishops = board.by_type(PieceType::Bishop);
+    // A lone minor piece, or any number of bishops that all share a square
+    // color.
+    (knights | bishops).count_ones() <= 1
+        || (knights == 0 && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0))
+}
+
+/// Returns true if `color` could still mate by some series of legal moves.
+/// A lone knight or bishops all on one square color can only mate when the
+/// other side has a piece of its own to hem its king in.
+pub fn has_mating_material(board: &Board, color: Color) -> bool {
+    let own = board.by_color(color);
+    let heavy = board.by_type(PieceType::Pawn)
+        | board.by_type(PieceType::Rook)
+        | board.by_type(PieceType::Queen);
+    if own & heavy != 0 {
+        return true;
+    }
+
+    let knights = own & board.by_type(PieceType::Knight);
+    let bishops = own & board.by_type(PieceType::Bishop);
+    let theirs = board.by_color(color.opposite()) & !board.by_type(PieceType::King);
+    if knights == 0 {
+        if bishops == 0 {
+            return false;
+        }
+        let shade = if bishops & LIGHT_SQUARES != 0 {
+            LIGHT_SQUARES
+        } else {
+            !LIGHT_SQUARES
+        };
+        if bishops & !shade != 0 {
+            return true;
+        }
+        // Bishops of the same shade never help mate on their own.
+        return theirs & !(board.by_type(PieceType::Bishop) & shade) != 0;
+    }
+    (knights | bishops).count_ones() > 1 || theirs != 0
+}
+
+/// Number of times the current position has occurred in the game.
+pub fn repetition_count(state: &GameState) -> usize {
+    let Some(current) = state.position_history.last() else {
+        return 0;
+    };
+    state
+        .position_history
+        .iter()
+        .filter(|key| *key == current)
+        .count()
+}
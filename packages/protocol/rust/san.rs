@@ -0,0 +1,207 @@
+use crate::rules;
+use crate::types::*;
+use std::fmt;
+
+fn piece_letter(piece: PieceType) -> &'static str {
+    match piece {
+        PieceType::Pawn => "",
+        PieceType::Knight => "N",
+        PieceType::Bishop => "B",
+        PieceType::Rook => "R",
+        PieceType::Queen => "Q",
+        PieceType::King => "K",
+    }
+}
+
+/// Renders a legal `move_` in Standard Algebraic Notation, e.g. `Nbd7`,
+/// `exd8=Q+` or `O-O-O`. `state` is the position before the move.
+pub fn to_san(state: &GameState, move_: &Move) -> String {
+    let board = &state.position.board;
+    let Some(piece) = board.piece_at(move_.from) else {
+        return format!("{}{}", move_.from, move_.to);
+    };
+    let from = move_.from.to_string();
+    let to = move_.to.to_string();
+    let file_of = |square: Square| square.file();
+    let rank_of = |square: Square| square.rank();
+
+    let mut san = String::new();
+    if piece.piece == PieceType::King && file_of(move_.from).abs_diff(file_of(move_.to)) == 2 {
+        san.push_str(if file_of(move_.to) > file_of(move_.from) {
+            "O-O"
+        } else {
+            "O-O-O"
+        });
+    } else {
+        let capture = board.piece_at(move_.to).is_some()
+            || (piece.piece == PieceType::Pawn && file_of(move_.from) != file_of(move_.to));
+
+        if piece.piece == PieceType::Pawn {
+            if capture {
+                san.push_str(&from[..1]);
+            }
+        } else {
+            san.push_str(piece_letter(piece.piece));
+            let rivals: Vec<Square> = rules::legal_moves(state)
+                .into_iter()
+                .filter(|m| m.to == move_.to && m.from != move_.from)
+                .filter(|m| board.piece_at(m.from).map(|p| p.piece) == Some(piece.piece))
+                .map(|m| m.from)
+                .collect();
+            if !rivals.is_empty() {
+                if rivals.iter().all(|&sq| file_of(sq) != file_of(move_.from)) {
+                    san.push_str(&from[..1]);
+                } else if rivals.iter().all(|&sq| rank_of(sq) != rank_of(move_.from)) {
+                    san.push_str(&from[1..]);
+                } else {
+                    san.push_str(&from);
+                }
+            }
+        }
+
+        if capture {
+            san.push('x');
+        }
+        san.push_str(&to);
+        if let Some(promotion) = move_.promotion {
+            san.push('=');
+            san.push_str(piece_letter(promotion));
+        }
+    }
+
+    let mut after = state.position;
+    after.play(move_);
+    if after.is_in_check() {
+        san.push(if after.legal_moves().is_empty() {
+            '#'
+        } else {
+            '+'
+        });
+    }
+    san
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SanError {
+    Invalid(String),
+    Illegal(String),
+    Ambiguous(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SanError::Invalid(san) => write!(f, "'{}' is not valid SAN", san),
+            SanError::Illegal(san) => write!(f, "'{}' is not a legal move here", san),
+            SanError::Ambiguous(san) => write!(f, "'{}' matches more than one move", san),
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+fn piece_from_letter(letter: char) -> Option<PieceType> {
+    match letter {
+        'N' => Some(PieceType::Knight),
+        'B' => Some(PieceType::Bishop),
+        'R' => Some(PieceType::Rook),
+        'Q' => Some(PieceType::Queen),
+        'K' => Some(PieceType::King),
+        _ => None,
+    }
+}
+
+/// Parses a move in Standard Algebraic Notation and resolves it against the
+/// legal moves in `state`. Check, mate and annotation suffixes are ignored,
+/// and castling may be written with zeros.
+pub fn parse_san(state: &GameState, san: &str) -> Result<Move, SanError> {
+    let invalid = || SanError::Invalid(san.to_string());
+    let text = san.trim_end_matches(['+', '#', '!', '?']);
+    let legal = rules::legal_moves(state);
+    let piece_on = |square: Square| state.position.board.piece_at(square).map(|p| p.piece);
+
+    let castle_file = match text {
+        "O-O" | "0-0" => Some(6),
+        "O-O-O" | "0-0-0" => Some(2),
+        _ => None,
+    };
+    if let Some(file) = castle_file {
+        return legal
+            .into_iter()
+            .find(|m| {
+                piece_on(m.from) == Some(PieceType::King)
+                    && m.from.file() == 4
+                    && m.to.file() == file
+                    && m.from.rank() == m.to.rank()
+            })
+            .ok_or_else(|| SanError::Illegal(san.to_string()));
+    }
+
+    let (body, promotion) = match text.split_once('=') {
+        Some((body, letter)) => {
+            let mut chars = letter.chars();
+            let promotion = chars
+                .next()
+                .and_then(piece_from_letter)
+                .ok_or_else(invalid)?;
+            if chars.next().is_some() {
+                return Err(invalid());
+            }
+            (body, Some(promotion))
+        }
+        // Some writers leave out the `=`, as in `e8Q`.
+        None => match text.chars().last().and_then(piece_from_letter) {
+            Some(promotion) if text.len() > 2 => (&text[..text.len() - 1], Some(promotion)),
+            _ => (text, None),
+        },
+    };
+
+    let (piece, rest) = match body.chars().next().and_then(piece_from_letter) {
+        Some(piece) => (piece, &body[1..]),
+        None => (PieceType::Pawn, body),
+    };
+    if rest.len() < 2 || !rest.is_char_boundary(rest.len() - 2) {
+        return Err(invalid());
+    }
+    let (qualifier, target) = rest.split_at(rest.len() - 2);
+    let to: Square = target.parse().map_err(|_| invalid())?;
+
+    let mut from_file = None;
+    let mut from_rank = None;
+    for c in qualifier.trim_end_matches(['x', ':']).chars() {
+        match c {
+            'a'..='h' if from_file.is_none() => from_file = Some(c as u8 - b'a'),
+            '1'..='8' if from_rank.is_none() => from_rank = Some(c as u8 - b'1'),
+            _ => return Err(invalid()),
+        }
+    }
+    // A pawn named without a file moves straight ahead, so `b5` is never `axb5`.
+    if piece == PieceType::Pawn && from_file.is_none() {
+        from_file = Some(to.file());
+    }
+
+    let mut candidates = legal.into_iter().filter(|m| {
+        m.to == to
+            && m.promotion == promotion
+            && piece_on(m.from) == Some(piece)
+            && from_file.is_none_or(|file| m.from.file() == file)
+            && from_rank.is_none_or(|rank| m.from.rank() == rank)
+    });
+    match (candidates.next(), candidates.next()) {
+        (Some(move_), None) => Ok(move_),
+        (Some(_), Some(_)) => Err(SanError::Ambiguous(san.to_string())),
+        (None, _) => Err(SanError::Illegal(san.to_string())),
+    }
+}
+
+impl Move {
+    /// Resolves `san` against the legal moves in `state`. See [`parse_san`].
+    pub fn from_san(state: &GameState, san: &str) -> Result<Self, SanError> {
+        parse_san(state, san)
+    }
+
+    /// Renders the move in SAN; `state` is the position before the move.
+    pub fn to_san(&self, state: &GameState) -> String {
+        to_san(state, self)
+    }
+}
@@ -1,6 +1,12 @@
+use crate::board::Board;
+use crate::clock::{Clock, Correspondence, TimeControl};
+use crate::zobrist::{self, ZobristKey};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum PieceType {
     Rook,
     Knight,
@@ -10,19 +16,145 @@ pub enum PieceType {
     Pawn,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum Color {
     White,
     Black,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+impl PieceType {
+    /// Conventional material value in pawns; the king has none.
+    pub fn value(self) -> i32 {
+        match self {
+            PieceType::Pawn => 1,
+            PieceType::Knight | PieceType::Bishop => 3,
+            PieceType::Rook => 5,
+            PieceType::Queen => 9,
+            PieceType::King => 0,
+        }
+    }
+}
+
+impl Color {
+    pub fn opposite(self) -> Color {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub struct Piece {
     pub color: Color,
     pub piece: PieceType,
 }
 
-pub type Square = u8;
+/// A square on the board, stored as its index into `GameState::board`:
+/// `row * 8 + col` with a8 = 0 and h1 = 63. On the wire it is that index, and
+/// deserialization rejects anything off the board.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+#[serde(try_from = "u8", into = "u8")]
+pub struct Square(u8);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SquareError {
+    OutOfRange(u8),
+    InvalidName(String),
+}
+
+impl fmt::Display for SquareError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SquareError::OutOfRange(index) => {
+                write!(f, "square {} is off the board, expected 0 to 63", index)
+            }
+            SquareError::InvalidName(name) => write!(f, "'{}' is not a square name", name),
+        }
+    }
+}
+
+impl std::error::Error for SquareError {}
+
+impl Square {
+    pub const fn new(index: u8) -> Option<Self> {
+        if index < 64 {
+            Some(Self(index))
+        } else {
+            None
+        }
+    }
+
+    /// The square on `file` (0 for a, 7 for h) and `rank` (0 for the first
+    /// rank, 7 for the eighth).
+    pub const fn from_file_rank(file: u8, rank: u8) -> Option<Self> {
+        if file < 8 && rank < 8 {
+            Some(Self((7 - rank) * 8 + file))
+        } else {
+            None
+        }
+    }
+
+    /// Every square in board order, a8 first.
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..64).map(Self)
+    }
+
+    pub const fn index(self) -> usize {
+        self.0 as usize
+    }
+
+    /// 0 for the a-file through 7 for the h-file.
+    pub const fn file(self) -> u8 {
+        self.0 % 8
+    }
+
+    /// 0 for the first rank through 7 for the eighth.
+    pub const fn rank(self) -> u8 {
+        7 - self.0 / 8
+    }
+}
+
+impl TryFrom<u8> for Square {
+    type Error = SquareError;
+
+    fn try_from(index: u8) -> Result<Self, Self::Error> {
+        Self::new(index).ok_or(SquareError::OutOfRange(index))
+    }
+}
+
+impl From<Square> for u8 {
+    fn from(square: Square) -> u8 {
+        square.0
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            (b'a' + self.file()) as char,
+            (b'1' + self.rank()) as char
+        )
+    }
+}
+
+impl FromStr for Square {
+    type Err = SquareError;
+
+    /// Parses an algebraic square name such as `e4`.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.as_bytes() {
+            &[file @ b'a'..=b'h', rank @ b'1'..=b'8'] => {
+                Ok(Self::from_file_rank(file - b'a', rank - b'1').unwrap())
+            }
+            _ => Err(SquareError::InvalidName(name.to_string())),
+        }
+    }
+}
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct Move {
@@ -31,44 +163,375 @@ pub struct Move {
     pub promotion: Option<PieceType>,
 }
 
+/// Everything needed to continue play from a position: placement, side to
+/// move, castling and en passant rights, and the move counters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Position {
+    pub board: Board,
+    pub turn: Color,
+    pub castling_rights: CastlingRights,
+    pub en_passant: Option<Square>,
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+    /// Zobrist hash of the fields above, minus the counters. Kept up to date
+    /// by `Position::play`; call `zobrist::hash` after editing fields by hand.
+    pub zobrist: ZobristKey,
+}
+
+impl Position {
+    /// `board` with white to move, full castling rights and fresh counters.
+    pub fn new(board: Board) -> Self {
+        let mut position = Self {
+            board,
+            turn: Color::White,
+            castling_rights: CastlingRights::all(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            zobrist: ZobristKey::default(),
+        };
+        position.zobrist = zobrist::hash(&position);
+        position
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct GameState {
-    pub board: Vec<Option<Piece>>,
-    pub turn: Color,
+    #[serde(flatten)]
+    pub position: Position,
     pub move_history: Vec<Move>,
     pub taken_pieces: TakenPieces,
+    /// White's material minus black's, in pawns.
+    pub material_balance: i32,
+    pub result: Option<GameResult>,
+    pub termination: Option<Termination>,
+    /// Zobrist hash of every position reached so far, one per ply, starting
+    /// with the initial position.
+    pub position_history: Vec<ZobristKey>,
+    /// Remaining time for both players, for games with a time control.
+    #[serde(default)]
+    pub clock: Option<Clock>,
+    /// Unix time in milliseconds by which the side to move must move, for
+    /// correspondence games.
+    #[serde(default)]
+    pub move_deadline: Option<u64>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+impl GameState {
+    /// Creates a game from `board` with white to move and full castling rights.
+    pub fn new(board: Board) -> Self {
+        Self::from_position(Position::new(board))
+    }
+
+    /// Creates a game that starts at `position`, with its result already set
+    /// if the position is terminal.
+    pub fn from_position(position: Position) -> Self {
+        let mut state = Self {
+            position,
+            move_history: vec![],
+            taken_pieces: TakenPieces::default(),
+            material_balance: crate::rules::material_balance(&position.board),
+            result: None,
+            termination: None,
+            position_history: vec![position.zobrist],
+            clock: None,
+            move_deadline: None,
+        };
+        if let Some((result, termination)) = crate::rules::outcome(&state) {
+            state.result = Some(result);
+            state.termination = Some(termination);
+        }
+        state
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+}
+
+impl GameResult {
+    pub fn win_for(color: Color) -> Self {
+        match color {
+            Color::White => GameResult::WhiteWins,
+            Color::Black => GameResult::BlackWins,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    InsufficientMaterial,
+    FiftyMoveRule,
+    SeventyFiveMoveRule,
+    ThreefoldRepetition,
+    FivefoldRepetition,
+    Resignation,
+    Agreement,
+    Timeout,
+    /// The flag fell, but the opponent could not have mated.
+    TimeoutVsInsufficientMaterial,
+    /// A player disconnected and did not come back in time.
+    Abandonment,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct CastlingRights {
+    pub white_kingside: bool,
+    pub white_queenside: bool,
+    pub black_kingside: bool,
+    pub black_queenside: bool,
+}
+
+impl CastlingRights {
+    pub fn all() -> Self {
+        Self {
+            white_kingside: true,
+            white_queenside: true,
+            black_kingside: true,
+            black_queenside: true,
+        }
+    }
+
+    pub fn none() -> Self {
+        Self {
+            white_kingside: false,
+            white_queenside: false,
+            black_kingside: false,
+            black_queenside: false,
+        }
+    }
+
+    pub fn kingside(&self, color: Color) -> bool {
+        match color {
+            Color::White => self.white_kingside,
+            Color::Black => self.black_kingside,
+        }
+    }
+
+    pub fn queenside(&self, color: Color) -> bool {
+        match color {
+            Color::White => self.white_queenside,
+            Color::Black => self.black_queenside,
+        }
+    }
+}
+
+/// Pieces each side has captured, in the order they were taken.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct TakenPieces {
     white: Vec<Piece>,
     black: Vec<Piece>,
 }
 
+impl TakenPieces {
+    /// Pieces captured by white.
+    pub fn white(&self) -> &[Piece] {
+        &self.white
+    }
+
+    /// Pieces captured by black.
+    pub fn black(&self) -> &[Piece] {
+        &self.black
+    }
+
+    pub fn by(&self, color: Color) -> &[Piece] {
+        match color {
+            Color::White => &self.white,
+            Color::Black => &self.black,
+        }
+    }
+
+    pub fn push(&mut self, by: Color, piece: Piece) {
+        match by {
+            Color::White => self.white.push(piece),
+            Color::Black => self.black.push(piece),
+        }
+    }
+
+    pub fn pop(&mut self, by: Color) -> Option<Piece> {
+        match by {
+            Color::White => self.white.pop(),
+            Color::Black => self.black.pop(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(tag = "type")]
 pub enum ClientMessage {
-    Identify { id: Uuid },
-    MakeMove { move_: Move },
-    JoinGame { game_id: Uuid },
-    TakePiece { from: Square, to: Square },
+    Identify {
+        id: Uuid,
+    },
+    MakeMove {
+        move_: Move,
+    },
+    JoinGame {
+        game_id: Uuid,
+    },
+    /// Deprecated since protocol 1.3.0: captures are detected from `MakeMove`,
+    /// and the server answers this message with an error.
+    TakePiece {
+        from: Square,
+        to: Square,
+    },
     Resign,
+    ClaimDraw,
+    OfferDraw,
+    AcceptDraw,
+    DeclineDraw,
+    RequestTakeback,
+    AcceptTakeback,
+    DeclineTakeback,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 #[serde(tag = "type")]
 pub enum ServerMessage {
-    GameState { state: GameState },
-    MoveMade { move_: Move },
-    Error { message: String },
-    ColorAssigned { color: Color },
+    GameState {
+        // Boxed to keep the other messages small.
+        state: Box<GameState>,
+        /// Legal moves for the side to move, grouped by origin square. Empty
+        /// once the game is over.
+        #[serde(with = "moves_by_square")]
+        legal_moves: BTreeMap<Square, Vec<Move>>,
+        in_check: bool,
+        /// Squares of the pieces giving check.
+        checkers: Vec<Square>,
+    },
+    MoveMade {
+        move_: Move,
+        /// The move in Standard Algebraic Notation, e.g. `Nbd7` or `exd8=Q+`.
+        san: String,
+        /// The move in UCI long algebraic notation, e.g. `e7e8q`.
+        uci: String,
+    },
+    Error {
+        message: String,
+    },
+    ColorAssigned {
+        color: Color,
+    },
     GameStarted,
-    WaitingForPlayers { connected_count: u8 },
+    WaitingForPlayers {
+        connected_count: u8,
+        spectator_count: u8,
+    },
+    /// Sent instead of `ColorAssigned` when both seats are taken; the client
+    /// can watch but not play.
+    JoinedAsSpectator,
+    SpectatorCount {
+        count: u8,
+    },
+    /// Both seats and every spectator place are taken; the client did not
+    /// join.
+    RoomFull,
+    GameOver {
+        result: GameResult,
+        reason: Termination,
+    },
+    DrawOffered {
+        by: Color,
+    },
+    /// `by` declined the offer, either outright or by replying with a move.
+    DrawDeclined {
+        by: Color,
+    },
+    DrawOfferCancelled {
+        by: Color,
+    },
+    TakebackRequested {
+        by: Color,
+    },
+    TakebackAccepted {
+        by: Color,
+    },
+    TakebackDeclined {
+        by: Color,
+    },
+    TakebackCancelled {
+        by: Color,
+    },
+    /// A player's connection dropped. They keep their seat for
+    /// `reconnect_within_ms`, or indefinitely when it is `None`.
+    PlayerDisconnected {
+        color: Color,
+        reconnect_within_ms: Option<u64>,
+    },
+    PlayerReconnected {
+        color: Color,
+    },
+}
+
+/// JSON object keys are strings, so the origin squares go over the wire as
+/// their decimal index, e.g. `"52"`.
+mod moves_by_square {
+    use super::{Move, Square};
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::collections::BTreeMap;
+
+    pub fn serialize<S: Serializer>(
+        moves: &BTreeMap<Square, Vec<Move>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(
+            moves
+                .iter()
+                .map(|(from, moves)| (from.index().to_string(), moves)),
+        )
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<Square, Vec<Move>>, D::Error> {
+        BTreeMap::<String, Vec<Move>>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(key, moves)| {
+                let index: u8 = key.parse().map_err(D::Error::custom)?;
+                let from = Square::try_from(index).map_err(D::Error::custom)?;
+                Ok((from, moves))
+            })
+            .collect()
+    }
+}
+
+impl ServerMessage {
+    /// A `GameState` message for `state` with its legal moves and check
+    /// information filled in.
+    pub fn game_state(state: GameState) -> Self {
+        let mut legal_moves: BTreeMap<Square, Vec<Move>> = BTreeMap::new();
+        for move_ in crate::rules::legal_moves(&state) {
+            legal_moves.entry(move_.from).or_default().push(move_);
+        }
+        ServerMessage::GameState {
+            legal_moves,
+            in_check: state.position.is_in_check(),
+            checkers: state.position.checkers(),
+            state: Box::new(state),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct NewGameBody {
     pub color: Color,
+    /// Optional FEN of the position the game starts from.
+    #[serde(default)]
+    pub fen: Option<String>,
+    /// Optional PGN of a game to continue; its moves are replayed first.
+    #[serde(default)]
+    pub pgn: Option<String>,
+    /// Optional time control; games without one are untimed.
+    #[serde(default)]
+    pub time_control: Option<TimeControl>,
+    /// Optional correspondence time control, instead of `time_control`.
+    #[serde(default)]
+    pub correspondence: Option<Correspondence>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -76,3 +539,69 @@ pub struct NewGameResponse {
     #[serde(rename = "gameId")]
     pub game_id: Uuid,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum RoomStatus {
+    WaitingForOpponent,
+    InProgress,
+    Finished,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum TimeControlKind {
+    Untimed,
+    Timed,
+    Correspondence,
+}
+
+/// One room in the `GET /api/games` listing.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct GameSummary {
+    #[serde(rename = "gameId")]
+    pub game_id: Uuid,
+    pub status: RoomStatus,
+    pub players: u8,
+    pub spectators: u8,
+    /// The color a new player would get, if a seat is free.
+    pub open_color: Option<Color>,
+    pub time_control: Option<TimeControl>,
+    pub correspondence: Option<Correspondence>,
+    pub result: Option<GameResult>,
+    /// Half-moves played so far.
+    pub plies: usize,
+    /// Unix time in milliseconds.
+    pub created_at: u64,
+}
+
+impl GameSummary {
+    pub fn time_control_kind(&self) -> TimeControlKind {
+        match (self.time_control, self.correspondence) {
+            (Some(_), _) => TimeControlKind::Timed,
+            (None, Some(_)) => TimeControlKind::Correspondence,
+            (None, None) => TimeControlKind::Untimed,
+        }
+    }
+}
+
+/// Query string for `GET /api/games`. Every filter is optional.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct GameListQuery {
+    #[serde(default)]
+    pub status: Option<RoomStatus>,
+    #[serde(default)]
+    pub kind: Option<TimeControlKind>,
+    #[serde(default)]
+    pub offset: usize,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// A page of rooms, newest first, with the number of rooms matching the
+/// filters across all pages.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct GameList {
+    pub games: Vec<GameSummary>,
+    pub total: usize,
+    pub offset: usize,
+    pub limit: usize,
+}
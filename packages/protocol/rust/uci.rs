@@ -0,0 +1,64 @@
+use crate::types::*;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UciError {
+    WrongLength(String),
+    InvalidSquare(String),
+    InvalidPromotion(char),
+}
+
+impl fmt::Display for UciError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UciError::WrongLength(uci) => {
+                write!(f, "'{}' is not 4 or 5 characters of long algebraic", uci)
+            }
+            UciError::InvalidSquare(square) => write!(f, "'{}' is not a square", square),
+            UciError::InvalidPromotion(c) => write!(f, "'{}' is not a promotion piece", c),
+        }
+    }
+}
+
+impl std::error::Error for UciError {}
+
+impl Move {
+    /// Parses UCI long algebraic notation such as `e2e4` or `e7e8q`. Castling
+    /// is written as the king's move, e.g. `e1g1`.
+    pub fn from_uci(uci: &str) -> Result<Self, UciError> {
+        if !uci.is_ascii() || !(4..=5).contains(&uci.len()) {
+            return Err(UciError::WrongLength(uci.to_string()));
+        }
+        let square = |name: &str| {
+            name.parse::<Square>()
+                .map_err(|_| UciError::InvalidSquare(name.to_string()))
+        };
+        let promotion = match uci[4..].chars().next() {
+            None => None,
+            Some(c) => Some(match c {
+                'q' => PieceType::Queen,
+                'r' => PieceType::Rook,
+                'b' => PieceType::Bishop,
+                'n' => PieceType::Knight,
+                _ => return Err(UciError::InvalidPromotion(c)),
+            }),
+        };
+        Ok(Move {
+            from: square(&uci[..2])?,
+            to: square(&uci[2..4])?,
+            promotion,
+        })
+    }
+
+    /// Renders the move in UCI long algebraic notation.
+    pub fn to_uci(&self) -> String {
+        let promotion = match self.promotion {
+            Some(PieceType::Queen) => "q",
+            Some(PieceType::Rook) => "r",
+            Some(PieceType::Bishop) => "b",
+            Some(PieceType::Knight) => "n",
+            _ => "",
+        };
+        format!("{}{}{}", self.from, self.to, promotion)
+    }
+}
@@ -0,0 +1,145 @@
+use crate::board::{self, squares};
+use crate::types::*;
+use std::fmt;
+
+// The keys are generated at compile time from a fixed seed, so a position
+// hashes to the same value in every build. Changing the seed or the order the
+// keys are drawn in changes every hash clients may have stored.
+
+const SEED: u64 = 0x4368_6573_7369_6361;
+
+struct Keys {
+    pieces: [[u64; 64]; 12],
+    black_to_move: u64,
+    // White kingside, white queenside, black kingside, black queenside.
+    castling: [u64; 4],
+    en_passant_file: [u64; 8],
+}
+
+const fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+const fn generate_keys() -> Keys {
+    let mut state = SEED;
+    let mut pieces = [[0; 64]; 12];
+    let mut piece = 0;
+    while piece < 12 {
+        let mut square = 0;
+        while square < 64 {
+            pieces[piece][square] = splitmix64(&mut state);
+            square += 1;
+        }
+        piece += 1;
+    }
+    let black_to_move = splitmix64(&mut state);
+    let mut castling = [0; 4];
+    let mut i = 0;
+    while i < 4 {
+        castling[i] = splitmix64(&mut state);
+        i += 1;
+    }
+    let mut en_passant_file = [0; 8];
+    let mut i = 0;
+    while i < 8 {
+        en_passant_file[i] = splitmix64(&mut state);
+        i += 1;
+    }
+    Keys {
+        pieces,
+        black_to_move,
+        castling,
+        en_passant_file,
+    }
+}
+
+const KEYS: Keys = generate_keys();
+
+/// A Zobrist hash of a position. Serialized as 16 lowercase hex digits, since
+/// JSON numbers cannot hold every 64-bit value exactly.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(into = "String", try_from = "String")]
+pub struct ZobristKey(pub u64);
+
+impl fmt::Display for ZobristKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl From<ZobristKey> for String {
+    fn from(key: ZobristKey) -> Self {
+        key.to_string()
+    }
+}
+
+impl TryFrom<String> for ZobristKey {
+    type Error = std::num::ParseIntError;
+
+    fn try_from(hex: String) -> Result<Self, Self::Error> {
+        u64::from_str_radix(&hex, 16).map(ZobristKey)
+    }
+}
+
+/// Key for `piece` standing on `square`; an empty square contributes nothing.
+pub fn piece_key(piece: Option<Piece>, square: Square) -> u64 {
+    match piece {
+        Some(piece) => {
+            let index = board::color_index(piece.color) * 6 + board::piece_index(piece.piece);
+            KEYS.pieces[index][square.index()]
+        }
+        None => 0,
+    }
+}
+
+pub fn side_key(turn: Color) -> u64 {
+    match turn {
+        Color::White => 0,
+        Color::Black => KEYS.black_to_move,
+    }
+}
+
+pub fn castling_key(rights: CastlingRights) -> u64 {
+    [
+        rights.white_kingside,
+        rights.white_queenside,
+        rights.black_kingside,
+        rights.black_queenside,
+    ]
+    .into_iter()
+    .zip(KEYS.castling)
+    .filter(|(allowed, _)| *allowed)
+    .fold(0, |key, (_, castling)| key ^ castling)
+}
+
+/// The en passant file counts only when the side to move can actually make
+/// the capture, so positions that repeat in the FIDE sense hash alike.
+pub fn en_passant_key(position: &Position) -> u64 {
+    match position.en_passant {
+        Some(target) if position.can_capture_en_passant() => {
+            KEYS.en_passant_file[target.file() as usize]
+        }
+        _ => 0,
+    }
+}
+
+/// Hashes `position` from scratch. `Position::play` keeps `zobrist` up to
+/// date incrementally; this is for positions built any other way.
+pub fn hash(position: &Position) -> ZobristKey {
+    let board = &position.board;
+    let pieces = squares(board.occupied()).fold(0, |key, square| {
+        key ^ piece_key(board.piece_at(square), square)
+    });
+    ZobristKey(
+        pieces
+            ^ side_key(position.turn)
+            ^ castling_key(position.castling_rights)
+            ^ en_passant_key(position),
+    )
+}
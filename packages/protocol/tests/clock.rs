@@ -0,0 +1,77 @@
+use chessica_protocol::clock::{Clock, Delay, DelayMode, TimeControl};
+use chessica_protocol::types::Color;
+
+fn time_control(base_ms: u64, increment_ms: u64, delay: Option<Delay>) -> TimeControl {
+    TimeControl {
+        base_ms,
+        increment_ms,
+        delay,
+    }
+}
+
+#[test]
+fn increment_is_added_after_the_move() {
+    let mut clock = Clock::new(time_control(60_000, 2_000, None), Color::White);
+    clock.press(5_000);
+    assert_eq!(clock.remaining(Color::White), 57_000);
+    assert_eq!(clock.running, Some(Color::Black));
+    assert_eq!(clock.time_to_flag(1_000), Some(59_000));
+}
+
+#[test]
+fn simple_delay_is_not_charged() {
+    let delay = Delay {
+        mode: DelayMode::Simple,
+        ms: 3_000,
+    };
+    let mut clock = Clock::new(time_control(10_000, 0, Some(delay)), Color::White);
+    assert_eq!(clock.time_left(Color::White, 2_000), 10_000);
+    assert_eq!(clock.time_to_flag(0), Some(13_000));
+    assert!(!clock.flagged(12_999));
+    assert!(clock.flagged(13_000));
+    clock.press(4_000);
+    assert_eq!(clock.remaining(Color::White), 9_000);
+}
+
+#[test]
+fn bronstein_delay_refunds_up_to_the_delay() {
+    let delay = Delay {
+        mode: DelayMode::Bronstein,
+        ms: 3_000,
+    };
+    let mut clock = Clock::new(time_control(10_000, 0, Some(delay)), Color::White);
+    clock.press(2_000);
+    assert_eq!(clock.remaining(Color::White), 10_000);
+    clock.press(5_000);
+    assert_eq!(clock.remaining(Color::Black), 8_000);
+}
+
+#[test]
+fn stop_charges_the_running_side() {
+    let mut clock = Clock::new(time_control(10_000, 1_000, None), Color::Black);
+    clock.stop(4_000);
+    assert_eq!(clock.remaining(Color::Black), 6_000);
+    assert_eq!(clock.running, None);
+    assert_eq!(clock.time_to_flag(0), None);
+    assert!(!clock.flagged(u64::MAX));
+}
+
+#[test]
+fn huge_values_saturate() {
+    for delay in [
+        Delay {
+            mode: DelayMode::Simple,
+            ms: 1,
+        },
+        Delay {
+            mode: DelayMode::Bronstein,
+            ms: 1,
+        },
+    ] {
+        let mut clock = Clock::new(time_control(u64::MAX, 1, Some(delay)), Color::White);
+        assert_eq!(clock.time_to_flag(0), Some(u64::MAX));
+        assert!(!clock.flagged(1_000));
+        clock.press(0);
+        assert_eq!(clock.remaining(Color::White), u64::MAX);
+    }
+}
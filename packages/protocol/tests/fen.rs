@@ -0,0 +1,147 @@
+use chessica_protocol::fen::{FenError, STARTING_FEN};
+use chessica_protocol::rules;
+use chessica_protocol::types::*;
+
+const POSITIONS: [&str; 6] = [
+    STARTING_FEN,
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+];
+
+fn state(fen: &str) -> GameState {
+    GameState::from_fen(fen).unwrap()
+}
+
+fn square(name: &str) -> Square {
+    name.parse().unwrap()
+}
+
+#[test]
+fn fen_round_trip() {
+    for fen in POSITIONS {
+        assert_eq!(state(fen).to_fen(), fen);
+    }
+}
+
+#[test]
+fn fen_counters_default_when_omitted() {
+    let position = Position::from_fen("k7/8/8/8/8/8/8/K6R b - -").unwrap();
+    assert_eq!(position.halfmove_clock, 0);
+    assert_eq!(position.fullmove_number, 1);
+}
+
+#[test]
+fn fen_rejects_out_of_range_counters() {
+    for (fen, error) in [
+        (
+            "k7/8/8/8/8/8/8/K6R b - - 0 4294967295",
+            FenError::InvalidFullmoveNumber("4294967295".to_string()),
+        ),
+        (
+            "k7/8/8/8/8/8/8/K6R w - - 4294967295 1",
+            FenError::InvalidHalfmoveClock("4294967295".to_string()),
+        ),
+        (
+            "k7/8/8/8/8/8/8/K6R w - - 0 0",
+            FenError::InvalidFullmoveNumber("0".to_string()),
+        ),
+        (
+            "k7/8/8/8/8/8/8/K6R w - - -1 1",
+            FenError::InvalidHalfmoveClock("-1".to_string()),
+        ),
+    ] {
+        assert_eq!(Position::from_fen(fen), Err(error), "{}", fen);
+    }
+}
+
+#[test]
+fn move_counters_saturate() {
+    let mut game = state("k7/8/8/8/8/8/8/K6R b - - 0 10000");
+    for uci in ["a8b8", "h1h2"] {
+        rules::apply_move(&mut game, &Move::from_uci(uci).unwrap()).unwrap();
+    }
+    assert!(game.to_fen().ends_with(" 2 10001"));
+
+    game.position.halfmove_clock = u32::MAX;
+    game.position.fullmove_number = u32::MAX;
+    rules::apply_move(&mut game, &Move::from_uci("b8a8").unwrap()).unwrap();
+    assert_eq!(game.position.halfmove_clock, u32::MAX);
+    assert_eq!(game.position.fullmove_number, u32::MAX);
+}
+
+#[test]
+fn fen_rejects_duplicate_castling_rights() {
+    for field in ["KK", "KQkqK", "qq"] {
+        let fen = format!("r3k2r/8/8/8/8/8/8/R3K2R w {} - 0 1", field);
+        assert_eq!(
+            Position::from_fen(&fen),
+            Err(FenError::InvalidCastling(field.to_string())),
+            "{}",
+            fen
+        );
+    }
+}
+
+#[test]
+fn fen_rejects_castling_rights_without_king_and_rook() {
+    assert!(Position::from_fen("r3k3/8/8/8/8/8/8/R3K2R w KQkq - 0 1").is_err());
+    assert!(Position::from_fen("r3k2r/8/8/8/8/8/8/R4K1R w KQ - 0 1").is_err());
+}
+
+#[test]
+fn fen_en_passant_needs_a_double_step() {
+    let ok = "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3";
+    assert_eq!(
+        Position::from_fen(ok).unwrap().en_passant,
+        Some(square("f6"))
+    );
+
+    for (fen, field) in [
+        // The pawn could not have come from f7.
+        (
+            "rnbqkbnr/ppp1ppp1/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "f6",
+        ),
+        // No pawn in front of the target.
+        (
+            "rnbqkbnr/ppp1p1pp/8/3pP3/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "f6",
+        ),
+        // Wrong rank for the side to move.
+        (
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR b KQkq f6 0 3",
+            "f6",
+        ),
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq z9 0 1",
+            "z9",
+        ),
+    ] {
+        assert_eq!(
+            Position::from_fen(fen),
+            Err(FenError::InvalidEnPassant(field.to_string())),
+            "{}",
+            fen
+        );
+    }
+}
+
+#[test]
+fn fen_rejects_malformed_boards() {
+    for fen in [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1",
+        "rnbqkbnr/pppppppp/9/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "rnbqkbnr/ppxppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "rnbqqbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "Pnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0",
+        // The side not to move is in check.
+        "k7/8/8/8/8/8/8/R3K3 w - - 0 1",
+    ] {
+        assert!(Position::from_fen(fen).is_err(), "{}", fen);
+    }
+}
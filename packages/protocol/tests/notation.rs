@@ -0,0 +1,94 @@
+use chessica_protocol::fen::STARTING_FEN;
+use chessica_protocol::rules;
+use chessica_protocol::san::{parse_san, to_san, SanError};
+use chessica_protocol::types::*;
+use chessica_protocol::uci::UciError;
+
+const POSITIONS: [&str; 6] = [
+    STARTING_FEN,
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+];
+
+fn state(fen: &str) -> GameState {
+    GameState::from_fen(fen).unwrap()
+}
+
+#[test]
+fn uci_round_trip() {
+    for uci in ["e2e4", "e1g1", "a7a8q", "h2h1n"] {
+        assert_eq!(Move::from_uci(uci).unwrap().to_uci(), uci);
+    }
+    assert_eq!(
+        Move::from_uci("e2e4e"),
+        Err(UciError::InvalidPromotion('e'))
+    );
+    assert_eq!(
+        Move::from_uci("e2e"),
+        Err(UciError::WrongLength("e2e".to_string()))
+    );
+    assert_eq!(
+        Move::from_uci("e9e4"),
+        Err(UciError::InvalidSquare("e9".to_string()))
+    );
+}
+
+#[test]
+fn san_round_trips_every_legal_move() {
+    for fen in POSITIONS {
+        let game = state(fen);
+        for move_ in rules::legal_moves(&game) {
+            let san = to_san(&game, &move_);
+            assert_eq!(parse_san(&game, &san), Ok(move_), "{} in {}", san, fen);
+        }
+    }
+}
+
+#[test]
+fn san_notation() {
+    let kiwipete = state(POSITIONS[1]);
+    let san = |uci: &str| to_san(&kiwipete, &Move::from_uci(uci).unwrap());
+    assert_eq!(san("e1g1"), "O-O");
+    assert_eq!(san("e1c1"), "O-O-O");
+    assert_eq!(san("e5f7"), "Nxf7");
+    assert_eq!(san("c3b1"), "Nb1");
+    assert_eq!(san("d2c1"), "Bc1");
+
+    let promotion = state("8/P6k/8/8/8/8/8/K7 w - - 0 1");
+    let a8q = Move::from_uci("a7a8q").unwrap();
+    assert_eq!(to_san(&promotion, &a8q), "a8=Q");
+    assert_eq!(parse_san(&promotion, "a8Q"), Ok(a8q.clone()));
+    assert_eq!(parse_san(&promotion, "a8=Q+!"), Ok(a8q));
+}
+
+#[test]
+fn san_parse_errors() {
+    let start = state(STARTING_FEN);
+    assert_eq!(
+        parse_san(&start, "e5"),
+        Err(SanError::Illegal("e5".to_string()))
+    );
+    assert_eq!(
+        parse_san(&start, "O-O"),
+        Err(SanError::Illegal("O-O".to_string()))
+    );
+    assert_eq!(
+        parse_san(&start, "Z"),
+        Err(SanError::Invalid("Z".to_string()))
+    );
+
+    // Both rooks reach d1.
+    let rooks = state("1k6/8/8/8/8/8/4K3/R6R w - - 0 1");
+    assert_eq!(
+        parse_san(&rooks, "Rd1"),
+        Err(SanError::Ambiguous("Rd1".to_string()))
+    );
+    assert_eq!(
+        parse_san(&rooks, "Rhd1"),
+        Ok(Move::from_uci("h1d1").unwrap())
+    );
+    assert_eq!(to_san(&rooks, &Move::from_uci("a1d1").unwrap()), "Rad1");
+}
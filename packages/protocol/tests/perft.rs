@@ -0,0 +1,72 @@
+use chessica_protocol::fen::STARTING_FEN;
+use chessica_protocol::perft::{divide, perft};
+use chessica_protocol::types::*;
+
+// Reference counts from https://www.chessprogramming.org/Perft_Results.
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+const POSITION_4_MIRRORED: &str =
+    "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1";
+const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+const POSITION_6: &str = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+fn assert_perft(fen: &str, expected: &[u64]) {
+    let position = Position::from_fen(fen).unwrap();
+    for (depth, &nodes) in (1..).zip(expected) {
+        assert_eq!(perft(&position, depth), nodes, "{} at depth {}", fen, depth);
+    }
+}
+
+#[test]
+fn starting_position() {
+    assert_perft(STARTING_FEN, &[20, 400, 8_902, 197_281]);
+}
+
+#[test]
+fn kiwipete() {
+    assert_perft(KIWIPETE, &[48, 2_039, 97_862]);
+}
+
+#[test]
+fn position_3_en_passant_and_pins() {
+    assert_perft(POSITION_3, &[14, 191, 2_812, 43_238]);
+}
+
+#[test]
+fn position_4_promotions_and_castling() {
+    assert_perft(POSITION_4, &[6, 264, 9_467]);
+    assert_perft(POSITION_4_MIRRORED, &[6, 264, 9_467]);
+}
+
+#[test]
+fn position_5() {
+    assert_perft(POSITION_5, &[44, 1_486, 62_379]);
+}
+
+#[test]
+fn position_6() {
+    assert_perft(POSITION_6, &[46, 2_079, 89_890]);
+}
+
+#[test]
+#[ignore = "slow without optimizations; run with --release -- --ignored"]
+fn deep() {
+    assert_perft(STARTING_FEN, &[20, 400, 8_902, 197_281, 4_865_609]);
+    assert_perft(KIWIPETE, &[48, 2_039, 97_862, 4_085_603]);
+    assert_perft(POSITION_3, &[14, 191, 2_812, 43_238, 674_624]);
+    assert_perft(POSITION_4, &[6, 264, 9_467, 422_333]);
+    assert_perft(POSITION_5, &[44, 1_486, 62_379, 2_103_487]);
+}
+
+#[test]
+fn divide_sums_to_perft() {
+    let position = Position::from_fen(KIWIPETE).unwrap();
+    let split = divide(&position, 3);
+    assert_eq!(split.len(), 48);
+    assert_eq!(split.iter().map(|(_, nodes)| nodes).sum::<u64>(), 97_862);
+
+    let castle = Move::from_uci("e1g1").unwrap();
+    assert_eq!(split.iter().find(|(m, _)| *m == castle).unwrap().1, 2_059);
+}
@@ -0,0 +1,162 @@
+use chessica_protocol::fen::STARTING_FEN;
+use chessica_protocol::pgn::{parse_pgn, to_pgn, PgnError, PgnTags};
+use chessica_protocol::rules;
+use chessica_protocol::san::{parse_san, SanError};
+use chessica_protocol::types::*;
+
+fn state(fen: &str) -> GameState {
+    GameState::from_fen(fen).unwrap()
+}
+
+fn play(state: &mut GameState, moves: &[&str]) {
+    for san in moves {
+        let move_ = parse_san(state, san).unwrap();
+        rules::apply_move(state, &move_).unwrap();
+    }
+}
+
+#[test]
+fn exports_a_finished_game() {
+    let initial = state(STARTING_FEN);
+    let mut game = initial.clone();
+    play(&mut game, &["f3", "e5", "g4", "Qh4#"]);
+
+    assert_eq!(
+        to_pgn(&initial, &game, &PgnTags::default()),
+        "[Event \"?\"]\n\
+         [Site \"?\"]\n\
+         [Date \"????.??.??\"]\n\
+         [Round \"?\"]\n\
+         [White \"?\"]\n\
+         [Black \"?\"]\n\
+         [Result \"0-1\"]\n\
+         [Termination \"normal\"]\n\
+         \n\
+         1. f3 e5 2. g4 Qh4# {Black wins by checkmate} 0-1\n"
+    );
+}
+
+#[test]
+fn exports_a_game_in_progress_from_a_fen() {
+    let fen = "k7/8/8/8/8/8/8/K6R b - - 0 40";
+    let initial = state(fen);
+    let mut game = initial.clone();
+    play(&mut game, &["Kb7"]);
+    let tags = PgnTags {
+        white: "A \"quoted\" name".to_string(),
+        ..PgnTags::default()
+    };
+
+    let pgn = to_pgn(&initial, &game, &tags);
+    assert!(
+        pgn.contains("[White \"A \\\"quoted\\\" name\"]\n"),
+        "{}",
+        pgn
+    );
+    assert!(pgn.contains("[Result \"*\"]\n"), "{}", pgn);
+    assert!(
+        pgn.contains(&format!("[SetUp \"1\"]\n[FEN \"{}\"]\n", fen)),
+        "{}",
+        pgn
+    );
+    assert!(pgn.contains("[Termination \"unterminated\"]\n"), "{}", pgn);
+    assert!(pgn.ends_with("\n40... Kb7 *\n"), "{}", pgn);
+}
+
+#[test]
+fn wraps_long_movetext() {
+    let initial = state(STARTING_FEN);
+    let mut game = initial.clone();
+    play(
+        &mut game,
+        &[
+            "e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Ba4", "Nf6", "O-O", "Be7", "Re1", "b5", "Bb3",
+            "d6", "c3", "O-O", "h3", "Nb8", "d4", "Nbd7", "c4", "c6", "cxb5", "axb5", "Nc3", "Bb7",
+        ],
+    );
+
+    let pgn = to_pgn(&initial, &game, &PgnTags::default());
+    let movetext = pgn.split("\n\n").nth(1).unwrap();
+    assert!(movetext.lines().count() > 1);
+    assert!(
+        movetext.lines().all(|line| line.len() <= 80),
+        "{}",
+        movetext
+    );
+}
+
+#[test]
+fn round_trips_through_import() {
+    let initial = state(STARTING_FEN);
+    let mut game = initial.clone();
+    play(
+        &mut game,
+        &["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Bxc6", "dxc6", "O-O"],
+    );
+
+    let parsed = parse_pgn(&to_pgn(&initial, &game, &PgnTags::default())).unwrap();
+    assert_eq!(parsed.result, "*");
+    assert_eq!(parsed.tag("FEN"), None);
+    let (moves, replayed) = parsed.replay().unwrap();
+    assert_eq!(moves, game.move_history);
+    assert_eq!(replayed.to_fen(), game.to_fen());
+}
+
+#[test]
+fn round_trips_through_import_from_a_fen() {
+    let fen = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+    let initial = state(fen);
+    let mut game = initial.clone();
+    play(&mut game, &["dxc8=Q"]);
+
+    let parsed = parse_pgn(&to_pgn(&initial, &game, &PgnTags::default())).unwrap();
+    assert_eq!(parsed.tag("SetUp"), Some("1"));
+    assert_eq!(parsed.tag("FEN"), Some(fen));
+    let (moves, replayed) = parsed.replay().unwrap();
+    assert_eq!(moves, game.move_history);
+    assert_eq!(replayed.to_fen(), game.to_fen());
+}
+
+#[test]
+fn import_skips_comments_and_variations() {
+    let pgn = "[Event \"Test\"]\n\n1. e4 {best by test} e5 (1... c5 2. Nf3) 2.Nf3 $1 ; note\n2... Nc6!? 1-0";
+    let parsed = parse_pgn(pgn).unwrap();
+    assert_eq!(parsed.tag("Event"), Some("Test"));
+    assert_eq!(parsed.moves, ["e4", "e5", "Nf3", "Nc6"]);
+    assert_eq!(parsed.result, "1-0");
+    assert_eq!(parsed.replay().unwrap().0.len(), 4);
+}
+
+#[test]
+fn import_reads_only_the_first_game() {
+    let parsed = parse_pgn("[Event \"One\"]\n1. e4 *\n\n[Event \"Two\"]\n1. d4 *").unwrap();
+    assert_eq!(parsed.tag("Event"), Some("One"));
+    assert_eq!(parsed.moves, ["e4"]);
+}
+
+#[test]
+fn import_errors() {
+    assert_eq!(
+        parse_pgn("1. e4 {unfinished"),
+        Err(PgnError::UnterminatedComment)
+    );
+    assert_eq!(
+        parse_pgn("1. e4 (1. d4"),
+        Err(PgnError::UnbalancedVariation)
+    );
+    assert_eq!(parse_pgn("[Event \"x"), Err(PgnError::UnterminatedTag));
+    assert_eq!(
+        parse_pgn("1. e4 e5 2. Ke3").unwrap().replay(),
+        Err(PgnError::IllegalMove {
+            ply: 3,
+            token: "Ke3".to_string(),
+            error: SanError::Illegal("Ke3".to_string()),
+        })
+    );
+    assert!(matches!(
+        parse_pgn("[FEN \"8/8/8/8/8/8/8/8 w - - 0 1\"]\n*")
+            .unwrap()
+            .initial_state(),
+        Err(PgnError::InvalidFen(_))
+    ));
+}
@@ -0,0 +1,299 @@
+use chessica_protocol::fen::STARTING_FEN;
+use chessica_protocol::rules::{self, MoveError};
+use chessica_protocol::types::*;
+
+fn state(fen: &str) -> GameState {
+    GameState::from_fen(fen).unwrap()
+}
+
+fn play(state: &mut GameState, moves: &[&str]) {
+    for uci in moves {
+        rules::apply_move(state, &Move::from_uci(uci).unwrap()).unwrap();
+    }
+}
+
+#[test]
+fn checkmate_ends_the_game() {
+    let mut game = state(STARTING_FEN);
+    play(&mut game, &["f2f3", "e7e5", "g2g4", "d8h4"]);
+    assert_eq!(game.result, Some(GameResult::BlackWins));
+    assert_eq!(game.termination, Some(Termination::Checkmate));
+    assert!(rules::legal_moves(&game).is_empty());
+    assert_eq!(
+        rules::apply_move(&mut game, &Move::from_uci("e2e4").unwrap()),
+        Err(MoveError::GameOver)
+    );
+}
+
+#[test]
+fn stalemate_is_a_draw() {
+    let mut game = state("k7/8/8/2Q5/8/8/8/K7 w - - 0 1");
+    play(&mut game, &["c5b6"]);
+    assert_eq!(game.result, Some(GameResult::Draw));
+    assert_eq!(game.termination, Some(Termination::Stalemate));
+    assert!(!game.position.is_in_check());
+}
+
+#[test]
+fn terminal_positions_are_recognised_on_import() {
+    for (fen, expected) in [
+        (
+            "k7/1Q6/1K6/8/8/8/8/8 b - - 0 1",
+            Some((GameResult::WhiteWins, Termination::Checkmate)),
+        ),
+        (
+            "k7/8/1Q6/8/8/8/8/K7 b - - 0 1",
+            Some((GameResult::Draw, Termination::Stalemate)),
+        ),
+        // In check, but the king can take the queen.
+        ("k7/1Q6/8/8/8/8/8/K7 b - - 0 1", None),
+    ] {
+        let game = state(fen);
+        assert_eq!(game.result.zip(game.termination), expected, "{}", fen);
+    }
+}
+
+/// Knights out and back, returning to the position the game started from.
+const KNIGHT_SHUFFLE: [&str; 4] = ["g1f3", "g8f6", "f3g1", "f6g8"];
+
+#[test]
+fn fifty_move_rule_can_be_claimed() {
+    let mut game = state("4k3/8/8/8/8/8/4P3/4K2R w - - 98 60");
+    play(&mut game, &["h1h2"]);
+    assert_eq!(rules::claimable_draw(&game), None);
+    play(&mut game, &["e8d8"]);
+    assert_eq!(
+        rules::claimable_draw(&game),
+        Some(Termination::FiftyMoveRule)
+    );
+    assert_eq!(game.result, None);
+
+    // A pawn move starts the count again.
+    play(&mut game, &["e2e4"]);
+    assert_eq!(game.position.halfmove_clock, 0);
+    assert_eq!(rules::claimable_draw(&game), None);
+}
+
+#[test]
+fn seventy_five_move_rule_ends_the_game() {
+    let mut game = state("4k3/8/8/8/8/8/4P3/4K2R w - - 148 80");
+    play(&mut game, &["h1h2"]);
+    assert_eq!(game.result, None);
+    play(&mut game, &["e8d8"]);
+    assert_eq!(game.result, Some(GameResult::Draw));
+    assert_eq!(game.termination, Some(Termination::SeventyFiveMoveRule));
+}
+
+#[test]
+fn seventy_five_move_rule_yields_to_checkmate() {
+    let mut game = state("k7/8/1K6/8/8/8/8/7R w - - 149 80");
+    play(&mut game, &["h1h8"]);
+    assert_eq!(game.termination, Some(Termination::Checkmate));
+}
+
+#[test]
+fn threefold_repetition_can_be_claimed() {
+    let mut game = state(STARTING_FEN);
+    play(&mut game, &KNIGHT_SHUFFLE);
+    assert_eq!(rules::repetition_count(&game), 2);
+    assert_eq!(rules::claimable_draw(&game), None);
+    play(&mut game, &KNIGHT_SHUFFLE);
+    assert_eq!(rules::repetition_count(&game), 3);
+    assert_eq!(
+        rules::claimable_draw(&game),
+        Some(Termination::ThreefoldRepetition)
+    );
+    assert_eq!(game.result, None);
+}
+
+#[test]
+fn en_passant_right_makes_a_position_different() {
+    // After e2e4 black could take en passant; the same placement later on
+    // cannot, so it is a different position.
+    let mut game = state("4k1n1/8/8/8/3p4/8/4P3/4K1N1 w - - 0 1");
+    play(&mut game, &["e2e4"]);
+    assert_eq!(rules::repetition_count(&game), 1);
+    let shuffle = ["g8f6", "g1f3", "f6g8", "f3g1"];
+    play(&mut game, &shuffle);
+    assert_eq!(rules::repetition_count(&game), 1);
+    play(&mut game, &shuffle);
+    assert_eq!(rules::repetition_count(&game), 2);
+    assert_eq!(rules::claimable_draw(&game), None);
+    play(&mut game, &shuffle);
+    assert_eq!(
+        rules::claimable_draw(&game),
+        Some(Termination::ThreefoldRepetition)
+    );
+}
+
+#[test]
+fn en_passant_right_that_cannot_be_used_is_ignored() {
+    // Nothing can take on e3, so the double step repeats like any move.
+    let mut game = state("4k1n1/8/8/8/8/8/4P3/4K1N1 w - - 0 1");
+    play(&mut game, &["e2e4"]);
+    let shuffle = ["g8f6", "g1f3", "f6g8", "f3g1"];
+    play(&mut game, &shuffle);
+    assert_eq!(rules::repetition_count(&game), 2);
+}
+
+#[test]
+fn fivefold_repetition_ends_the_game() {
+    let mut game = state(STARTING_FEN);
+    for _ in 0..3 {
+        play(&mut game, &KNIGHT_SHUFFLE);
+    }
+    assert_eq!(game.result, None);
+    play(&mut game, &KNIGHT_SHUFFLE);
+    assert_eq!(rules::repetition_count(&game), 5);
+    assert_eq!(game.result, Some(GameResult::Draw));
+    assert_eq!(game.termination, Some(Termination::FivefoldRepetition));
+}
+
+#[test]
+fn insufficient_material() {
+    for (fen, insufficient) in [
+        ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", true),
+        ("4k3/8/8/8/8/8/8/2N1K3 w - - 0 1", true),
+        ("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1", true),
+        // Bishops on c1 and f8 are both on dark squares.
+        ("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1", true),
+        ("2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1", false),
+        ("4k3/8/8/8/8/8/8/1NN1K3 w - - 0 1", false),
+        ("4k3/8/8/8/8/8/8/2BNK3 w - - 0 1", false),
+        ("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", false),
+        ("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", false),
+    ] {
+        let board = Position::from_fen(fen).unwrap().board;
+        assert_eq!(
+            rules::is_insufficient_material(&board),
+            insufficient,
+            "{}",
+            fen
+        );
+    }
+}
+
+#[test]
+fn capturing_down_to_bare_kings_ends_the_game() {
+    let mut game = state("4k3/8/8/8/8/8/3r4/4K3 w - - 0 1");
+    play(&mut game, &["e1d2"]);
+    assert_eq!(game.result, Some(GameResult::Draw));
+    assert_eq!(game.termination, Some(Termination::InsufficientMaterial));
+}
+
+#[test]
+fn undo_restores_captures() {
+    let mut game = state(STARTING_FEN);
+    play(&mut game, &["e2e4", "d7d5"]);
+    let before = game.clone();
+    let undo = rules::apply_move(&mut game, &Move::from_uci("e4d5").unwrap()).unwrap();
+    assert_eq!(game.taken_pieces.white().len(), 1);
+    assert_eq!(game.material_balance, 1);
+
+    rules::undo_move(&mut game, &undo);
+    assert_eq!(game, before);
+    assert!(game.taken_pieces.white().is_empty());
+}
+
+#[test]
+fn undo_restores_castling_rights() {
+    let mut game = state("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    let before = game.clone();
+    let undo = rules::apply_move(&mut game, &Move::from_uci("e1g1").unwrap()).unwrap();
+    assert!(!game.position.castling_rights.kingside(Color::White));
+    assert!(!game.position.castling_rights.queenside(Color::White));
+
+    rules::undo_move(&mut game, &undo);
+    assert_eq!(game, before);
+    assert_eq!(game.to_fen(), "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+}
+
+#[test]
+fn undo_restores_en_passant() {
+    let mut game = state("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1");
+    play(&mut game, &["e2e4"]);
+    let before = game.clone();
+    let undo = rules::apply_move(&mut game, &Move::from_uci("d4e3").unwrap()).unwrap();
+    assert_eq!(game.to_fen(), "4k3/8/8/8/8/4p3/8/4K3 w - - 0 2");
+    assert_eq!(game.taken_pieces.black().len(), 1);
+
+    rules::undo_move(&mut game, &undo);
+    assert_eq!(game, before);
+    assert_eq!(game.position.en_passant, Some("e3".parse().unwrap()));
+    assert!(game.taken_pieces.black().is_empty());
+}
+
+#[test]
+fn undo_reopens_a_finished_game() {
+    let mut game = state(STARTING_FEN);
+    play(&mut game, &["f2f3", "e7e5", "g2g4"]);
+    let before = game.clone();
+    let undo = rules::apply_move(&mut game, &Move::from_uci("d8h4").unwrap()).unwrap();
+    assert_eq!(game.termination, Some(Termination::Checkmate));
+
+    rules::undo_move(&mut game, &undo);
+    assert_eq!(game, before);
+    assert!(!rules::legal_moves(&game).is_empty());
+}
+
+#[test]
+fn undo_then_replay_reaches_the_same_position() {
+    let mut game = state(STARTING_FEN);
+    let moves = [
+        "e2e4", "c7c5", "g1f3", "d7d6", "f1b5", "c8d7", "b5d7", "d8d7", "e1g1",
+    ];
+    let undos: Vec<_> = moves
+        .iter()
+        .map(|uci| rules::apply_move(&mut game, &Move::from_uci(uci).unwrap()).unwrap())
+        .collect();
+    let end = game.clone();
+    for undo in undos.iter().rev() {
+        rules::undo_move(&mut game, undo);
+    }
+    assert_eq!(game, state(STARTING_FEN));
+    play(&mut game, &moves);
+    assert_eq!(game, end);
+}
+
+#[test]
+fn mating_material() {
+    // Whether white, then black, could still mate. c1 and f8 are dark
+    // squares, f1 and c8 light ones.
+    for (fen, white, black) in [
+        ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", false, false),
+        ("4k3/8/8/8/8/8/8/3QK3 w - - 0 1", true, false),
+        ("4k3/8/8/8/8/8/8/R3K3 w - - 0 1", true, false),
+        ("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1", true, false),
+        // A lone minor piece needs something of the opponent's to mate
+        // against.
+        ("4k3/8/8/8/8/8/8/2N1K3 w - - 0 1", false, false),
+        ("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1", false, false),
+        ("4k3/4p3/8/8/8/8/8/2N1K3 w - - 0 1", true, true),
+        ("4k3/4p3/8/8/8/8/8/2B1K3 w - - 0 1", true, true),
+        ("2n1k3/8/8/8/8/8/8/2B1K3 w - - 0 1", true, true),
+        ("4kb2/8/8/8/8/8/8/2N1K3 w - - 0 1", true, true),
+        // Bishops on one shade cannot hem a king in with bishops of the
+        // same shade, but can with bishops of the other.
+        ("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1", false, false),
+        ("2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1", true, true),
+        ("2b1kb2/8/8/8/8/8/8/2B1K3 w - - 0 1", true, true),
+        ("4k3/8/8/8/8/8/8/2B1KB2 w - - 0 1", true, false),
+        ("4k3/8/8/8/8/8/3B4/2B1K3 w - - 0 1", false, false),
+        ("4k3/8/8/8/8/8/8/1NN1K3 w - - 0 1", true, false),
+        ("4k3/8/8/8/8/8/8/2BNK3 w - - 0 1", true, false),
+    ] {
+        let board = Position::from_fen(fen).unwrap().board;
+        assert_eq!(
+            rules::has_mating_material(&board, Color::White),
+            white,
+            "white in {}",
+            fen
+        );
+        assert_eq!(
+            rules::has_mating_material(&board, Color::Black),
+            black,
+            "black in {}",
+            fen
+        );
+    }
+}
@@ -0,0 +1,43 @@
+use chessica_protocol::types::*;
+
+fn square(name: &str) -> Square {
+    name.parse().unwrap()
+}
+
+#[test]
+fn square_names_round_trip() {
+    for square in Square::all() {
+        assert_eq!(square.to_string().parse::<Square>(), Ok(square));
+    }
+    assert_eq!(square("a1"), Square::from_file_rank(0, 0).unwrap());
+    assert_eq!(square("h8"), Square::from_file_rank(7, 7).unwrap());
+    for name in ["", "e", "i1", "a0", "a9", "E4", "e44"] {
+        assert_eq!(
+            name.parse::<Square>(),
+            Err(SquareError::InvalidName(name.to_string()))
+        );
+    }
+}
+
+#[test]
+fn file_and_rank_helpers() {
+    let e4 = square("e4");
+    assert_eq!((e4.file(), e4.rank()), (4, 3));
+    assert_eq!(Square::from_file_rank(4, 3), Some(e4));
+    assert_eq!(Square::from_file_rank(8, 0), None);
+    assert_eq!(Square::from_file_rank(0, 8), None);
+    assert_eq!(Square::all().next(), Some(square("a8")));
+    assert_eq!(Square::all().last(), Some(square("h1")));
+    assert_eq!(Square::all().count(), 64);
+}
+
+#[test]
+fn indices_are_validated() {
+    for index in 0..64u8 {
+        let square = Square::try_from(index).unwrap();
+        assert_eq!(square.index(), usize::from(index));
+        assert_eq!(u8::from(square), index);
+    }
+    assert_eq!(Square::new(64), None);
+    assert_eq!(Square::try_from(200), Err(SquareError::OutOfRange(200)));
+}
@@ -12,6 +12,7 @@ export interface Piece {
   piece: PieceType;
 }
 
+// Index into GameState.board, 0 (a8) to 63 (h1). The server rejects anything else.
 export type Square = number;
 
 export interface Move {
@@ -23,11 +24,71 @@ export interface Move {
 export interface GameState {
   board: (Piece | null)[];
   turn: Color;
-  moveHistory: Move[];
-  takenPieces: {
+  move_history: Move[];
+  // Pieces each side has captured, in the order they were taken.
+  taken_pieces: {
     white: Piece[];
     black: Piece[];
   };
+  // White's material minus black's, in pawns.
+  material_balance: number;
+  castling_rights: CastlingRights;
+  en_passant: Square | null;
+  result: GameResult | null;
+  termination: Termination | null;
+  halfmove_clock: number;
+  fullmove_number: number;
+  // Zobrist hashes as 16 hex digits; use BigInt("0x" + key) for arithmetic.
+  zobrist: string;
+  position_history: string[];
+  // Null for untimed games.
+  clock: Clock | null;
+  // Unix time in milliseconds, for correspondence games.
+  move_deadline: number | null;
+}
+
+export type DelayMode = "Simple" | "Bronstein";
+
+export interface TimeControl {
+  base_ms: number;
+  increment_ms?: number;
+  delay?: { mode: DelayMode; ms: number } | null;
+}
+
+export interface Correspondence {
+  days_per_move: number;
+}
+
+// The running side's time is as of the start of its turn; count down from
+// when the GameState arrived.
+export interface Clock {
+  time_control: TimeControl;
+  white_ms: number;
+  black_ms: number;
+  running: Color | null;
+}
+
+export type GameResult = "WhiteWins" | "BlackWins" | "Draw";
+
+export type Termination =
+  | "Checkmate"
+  | "Stalemate"
+  | "InsufficientMaterial"
+  | "FiftyMoveRule"
+  | "SeventyFiveMoveRule"
+  | "ThreefoldRepetition"
+  | "FivefoldRepetition"
+  | "Resignation"
+  | "Agreement"
+  | "Timeout"
+  | "TimeoutVsInsufficientMaterial"
+  | "Abandonment";
+
+export interface CastlingRights {
+  white_kingside: boolean;
+  white_queenside: boolean;
+  black_kingside: boolean;
+  black_queenside: boolean;
 }
 
 export type ClientMessage =
@@ -35,21 +96,89 @@ export type ClientMessage =
   | { type: "MakeMove"; move_: Move }
   | { type: "JoinGame"; game_id: string }
   | { type: "Resign" }
+  | { type: "ClaimDraw" }
+  | { type: "OfferDraw" }
+  | { type: "AcceptDraw" }
+  | { type: "DeclineDraw" }
+  | { type: "RequestTakeback" }
+  | { type: "AcceptTakeback" }
+  | { type: "DeclineTakeback" }
+  /** @deprecated since 1.3.0: captures are detected from MakeMove. */
   | { type: "TakePiece"; from: number; to: number };
 
 export type ServerMessage =
-  | { type: "GameState"; state: GameState }
-  | { type: "MoveMade"; move_: Move }
+  | {
+      type: "GameState";
+      state: GameState;
+      // Keyed by origin square; empty once the game is over.
+      legal_moves: Record<Square, Move[]>;
+      in_check: boolean;
+      checkers: Square[];
+    }
+  | { type: "MoveMade"; move_: Move; san: string; uci: string }
   | { type: "Error"; message: string }
   | { type: "ColorAssigned"; color: Color }
   | { type: "GameStarted" }
   //  | { type: "PiceTaken" } // MoveMade should overwrite it, and the data should be in GameState
-  | { type: "WaitingForPlayers"; connected_count: number };
+  | { type: "WaitingForPlayers"; connected_count: number; spectator_count: number }
+  // The room was full; the client watches without playing.
+  | { type: "JoinedAsSpectator" }
+  | { type: "SpectatorCount"; count: number }
+  // Not even a spectator place was left; the client did not join.
+  | { type: "RoomFull" }
+  | { type: "GameOver"; result: GameResult; reason: Termination }
+  | { type: "DrawOffered"; by: Color }
+  | { type: "DrawDeclined"; by: Color }
+  | { type: "DrawOfferCancelled"; by: Color }
+  | { type: "TakebackRequested"; by: Color }
+  | { type: "TakebackAccepted"; by: Color }
+  | { type: "TakebackDeclined"; by: Color }
+  | { type: "TakebackCancelled"; by: Color }
+  // reconnect_within_ms is null when the seat is kept indefinitely.
+  | { type: "PlayerDisconnected"; color: Color; reconnect_within_ms: number | null }
+  | { type: "PlayerReconnected"; color: Color };
 
 export interface NewGameBody {
   color: Color;
+  fen?: string;
+  pgn?: string;
+  time_control?: TimeControl;
+  correspondence?: Correspondence;
 }
 
 export interface NewGameResponse {
   gameId: string;
 }
+
+export type RoomStatus = "WaitingForOpponent" | "InProgress" | "Finished";
+
+export type TimeControlKind = "Untimed" | "Timed" | "Correspondence";
+
+export interface GameSummary {
+  gameId: string;
+  status: RoomStatus;
+  players: number;
+  spectators: number;
+  open_color: Color | null;
+  time_control: TimeControl | null;
+  correspondence: Correspondence | null;
+  result: GameResult | null;
+  plies: number;
+  // Unix time in milliseconds.
+  created_at: number;
+}
+
+// Query string for GET /api/games.
+export interface GameListQuery {
+  status?: RoomStatus;
+  kind?: TimeControlKind;
+  offset?: number;
+  limit?: number;
+}
+
+export interface GameList {
+  games: GameSummary[];
+  total: number;
+  offset: number;
+  limit: number;
+}